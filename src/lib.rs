@@ -1,10 +1,22 @@
-
 mod test;
 
 use std::{
-    alloc::{alloc, dealloc, Layout},
-    mem::align_of,
-    ops::{Deref, DerefMut},
+    alloc::{alloc, alloc_zeroed, dealloc, handle_alloc_error, GlobalAlloc, Layout},
+    any::{Any, TypeId},
+    cell::{Cell, RefCell, UnsafeCell},
+    collections::HashMap,
+    fmt,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    mem::{align_of, ManuallyDrop, MaybeUninit},
+    ops::{Deref, DerefMut, Index, IndexMut},
+    ptr::NonNull,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    thread::ThreadId,
 };
 
 use anyhow::bail;
@@ -19,9 +31,7 @@ where
 
 impl<T> Clone for RadPtr<T> {
     fn clone(&self) -> Self {
-        Self {
-            ptr: self.ptr.clone(),
-        }
+        Self { ptr: self.ptr }
     }
 }
 
@@ -47,16 +57,136 @@ impl<T> Deref for RadPtr<T> {
     type Target = T;
 }
 
+impl<T> RadPtr<T> {
+    /// Whether the pointee could be reinterpreted as a `U`: the address is
+    /// aligned for `U` and `U` is no larger than `T`.
+    pub fn can_cast_to<U>(&self) -> bool {
+        self.ptr.align_offset(align_of::<U>()) == 0
+            && std::mem::size_of::<U>() <= std::mem::size_of::<T>()
+    }
+
+    /// Whether the bytes behind `self` and `other` overlap, judging by their
+    /// addresses and type sizes. Zero-sized pointees never alias.
+    pub fn aliases<U>(&self, other: &RadPtr<U>) -> bool {
+        let (a, b) = (self.ptr as usize, other.ptr as usize);
+        let (a_len, b_len) = (std::mem::size_of::<T>(), std::mem::size_of::<U>());
+        a_len > 0 && b_len > 0 && a < b + b_len && b < a + a_len
+    }
+}
+
+#[cfg(feature = "bytemuck")]
+impl<T: bytemuck::Pod> RadPtr<T> {
+    /// Reinterprets the pointee as another plain-old-data type without copying.
+    ///
+    /// Panics if `U` differs in size from `T` or if the stored address is not
+    /// aligned for `U`.
+    pub fn cast<U: bytemuck::Pod>(self) -> RadPtr<U> {
+        assert_eq!(
+            std::mem::size_of::<T>(),
+            std::mem::size_of::<U>(),
+            "RadPtr::cast => Size mismatch between source and target types"
+        );
+        assert!(
+            self.ptr.align_offset(align_of::<U>()) == 0,
+            "RadPtr::cast => Pointer is not aligned for target type"
+        );
+        RadPtr {
+            ptr: self.ptr.cast::<U>(),
+        }
+    }
+}
+
+/// Allocates several values into an arena in one line: `alloc!(arena, a = x,
+/// b = y)` expands to `let a = arena.alloc(x)?; let b = arena.alloc(y)?;`.
+/// `arena` may be anything with an `alloc` method and is evaluated once per
+/// binding.
+#[macro_export]
+macro_rules! alloc {
+    ($arena:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $(let $name = $arena.alloc($value)?;)+
+    };
+}
+
+/// Cache line size assumed by the `*_cache_aligned` helpers.
+pub const CACHE_LINE_SIZE: usize = 64;
+
+/// Where an allocator's memory lives, which decides whether its allocations
+/// move when the allocator itself is moved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backing {
+    /// Stored inline in the allocator, so allocations move with it.
+    InlineStack,
+    /// Owned memory from the global allocator; addresses are stable.
+    Heap,
+    /// Memory lent by the caller or a parent arena; addresses are stable.
+    BorrowedSlice,
+    /// Memory mapped directly from the OS; addresses are stable.
+    Mmap,
+}
+
+/// `repr(C)` with the buffer first: it starts at offset 0, so moving the
+/// allocator keeps the base aligned to `align_of::<Self>()`.
+///
+/// Neither `Send` nor `Sync`: `alloc_with_drop` accepts `!Send` values,
+/// whose destructors must run on the thread that stored them.
+#[repr(C)]
 pub struct StackAllocator<const S: usize> {
-    stack: [u8; S],
+    stack: [MaybeUninit<u8>; S],
     top: usize,
+    drops: Vec<StackDropEntry>,
+    allocs: Vec<StackAllocEntry>,
+    _not_send: PhantomData<*const ()>,
+}
+
+/// Where each live allocation sits on the stack, so the top one can be
+/// recovered after its pointer has been dropped.
+struct StackAllocEntry {
+    offset: usize,
+    size: usize,
+}
+
+/// Destructor for a drop-tracked stack allocation. Stored by offset rather
+/// than address since the inline buffer moves with the allocator.
+struct StackDropEntry {
+    offset: usize,
+    size: usize,
+    drop_fn: unsafe fn(*mut u8),
+}
+
+unsafe fn drop_erased<T>(ptr: *mut u8) {
+    std::ptr::drop_in_place(ptr.cast::<T>())
+}
+
+impl<const S: usize> Default for StackAllocator<S> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<const S: usize> StackAllocator<S> {
     pub const fn new() -> Self {
         Self {
-            stack: [0; S],
+            stack: [MaybeUninit::uninit(); S],
             top: 0,
+            drops: Vec::new(),
+            allocs: Vec::new(),
+            _not_send: PhantomData,
+        }
+    }
+
+    /// Creates the allocator directly on the heap, so large `S` never has to
+    /// fit on the thread stack during construction.
+    pub fn boxed() -> Box<Self> {
+        unsafe {
+            let layout = Layout::new::<Self>();
+            let ptr = alloc_zeroed(layout).cast::<Self>();
+            if ptr.is_null() {
+                handle_alloc_error(layout);
+            }
+            std::ptr::addr_of_mut!((*ptr).top).write(0);
+            std::ptr::addr_of_mut!((*ptr).drops).write(Vec::new());
+            std::ptr::addr_of_mut!((*ptr).allocs).write(Vec::new());
+            Box::from_raw(ptr)
         }
     }
 
@@ -64,54 +194,338 @@ impl<const S: usize> StackAllocator<S> {
         self.stack.len()
     }
 
+    /// Bytes guaranteed usable by back-to-back `T` allocations on an empty
+    /// stack: the capacity minus the worst-case padding before the first one,
+    /// since the inline buffer's alignment isn't known until it is placed.
+    /// At least `usable_capacity::<T>() / size_of::<T>()` values always fit.
+    pub const fn usable_capacity<T>() -> usize {
+        S.saturating_sub(align_of::<T>() - 1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.top == 0
+    }
+
+    /// Bytes in use, including alignment padding.
+    pub fn used(&self) -> usize {
+        self.top
+    }
+
+    /// The unused space above the top, for an external writer to fill
+    /// before the written length is handed to `commit`.
+    pub fn remaining_mut(&mut self) -> &mut [MaybeUninit<u8>] {
+        let top = self.top;
+        unsafe { std::slice::from_raw_parts_mut(self.stack.as_mut_ptr().add(top), S - top) }
+    }
+
+    /// Marks the next `bytes` bytes above the top as used, after something
+    /// outside the allocator wrote them through `remaining_mut`. They become
+    /// an allocation of their own, freed by `shrink`/`popn` like any other.
+    ///
+    /// # Safety
+    /// The `bytes` bytes above the top must have been initialized.
+    pub unsafe fn commit(&mut self, bytes: usize) -> anyhow::Result<()> {
+        if bytes > S - self.top {
+            bail!("StackAllocator::commit => Committed bytes exceed the remaining space");
+        }
+        self.allocs.push(StackAllocEntry {
+            offset: self.top,
+            size: bytes,
+        });
+        self.top += bytes;
+        Ok(())
+    }
+
+    pub fn backing(&self) -> Backing {
+        Backing::InlineStack
+    }
+
     pub fn alloc<T>(&mut self, data: T) -> anyhow::Result<RadPtr<T>>
     where
         T: Sized,
     {
-        let data_size = std::mem::size_of::<T>();
-        if self.top + data_size > self.len() {
-            bail!("Stack allocator out of memory");
+        self.alloc_aligned(data, align_of::<T>())
+    }
+
+    /// Allocates `data` at an address aligned to `align` (or `T`'s own
+    /// alignment, if larger). `align` must be a power of two.
+    pub fn alloc_aligned<T>(&mut self, data: T, align: usize) -> anyhow::Result<RadPtr<T>> {
+        if !align.is_power_of_two() {
+            bail!("StackAllocator::alloc_aligned => Alignment must be a power of two");
         }
         unsafe {
-            // let offset = self.stack.as_mut_ptr().align_offset(align_of::<u8>());
-            let ptr = self.stack.as_mut_ptr().add(self.top);
-            let offset = ptr.align_offset(align_of::<T>());
-            let ptr = ptr.add(offset).cast::<T>();
+            let ptr = self
+                .bump(std::mem::size_of::<T>(), align.max(align_of::<T>()))?
+                .cast::<T>();
             std::ptr::write(ptr, data);
-            self.top += data_size + offset;
+            self.allocs.push(StackAllocEntry {
+                offset: ptr as usize - self.stack.as_ptr() as usize,
+                size: std::mem::size_of::<T>(),
+            });
 
             let sp = RadPtr { ptr };
             Ok(sp)
         }
     }
 
+    /// Allocates `data` and registers its destructor to run when the stack is
+    /// cleared, shrunk below it, or dropped.
+    ///
+    /// Destructors are guaranteed to run in reverse allocation order, so a
+    /// value's destructor may still use anything allocated before it.
+    ///
+    /// The destructor may run after the allocator has moved, so `T` must
+    /// be no more aligned than the allocator itself.
+    pub fn alloc_with_drop<T>(&mut self, data: T) -> anyhow::Result<RadPtr<T>> {
+        if align_of::<T>() > align_of::<Self>() {
+            bail!("StackAllocator::alloc_with_drop => Type is more aligned than the allocator");
+        }
+        let sp = self.alloc(data)?;
+        self.drops.push(StackDropEntry {
+            offset: sp.ptr as usize - self.stack.as_ptr() as usize,
+            size: std::mem::size_of::<T>(),
+            drop_fn: drop_erased::<T>,
+        });
+        Ok(sp)
+    }
+
+    /// Re-derives a pointer to the most recent allocation, provided it is the
+    /// same size as `T` and suitably aligned for it.
+    pub fn top_ptr<T>(&self) -> Option<RadPtr<T>> {
+        let entry = self.allocs.last()?;
+        let ptr = unsafe { self.stack.as_ptr().add(entry.offset) } as *mut T;
+        let matches = entry.size == std::mem::size_of::<T>() && ptr.is_aligned();
+        matches.then_some(RadPtr { ptr })
+    }
+
+    /// `(offset, size)` of each drop-tracked allocation still on the stack,
+    /// in allocation order. Destructors run in the reverse of this order.
+    pub fn drop_entries(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.drops.iter().map(|e| (e.offset, e.size))
+    }
+
+    /// Runs the destructor of every tracked value that overlaps `offset..`,
+    /// including one that `offset` lands inside.
+    fn run_drops_from(&mut self, offset: usize) {
+        while self
+            .drops
+            .last()
+            .is_some_and(|e| e.offset >= offset || e.offset + e.size > offset)
+        {
+            let entry = self.drops.pop().expect("checked above");
+            unsafe { (entry.drop_fn)(self.stack.as_mut_ptr().add(entry.offset).cast()) };
+        }
+    }
+
+    /// Allocates `data` on its own 64-byte cache line boundary, so values
+    /// written by different threads don't false-share.
+    pub fn alloc_cache_aligned<T>(&mut self, data: T) -> anyhow::Result<RadPtr<T>> {
+        self.alloc_aligned(data, CACHE_LINE_SIZE)
+    }
+
+    /// Whether `alloc::<T>()` would succeed right now, accounting for the
+    /// alignment padding needed at the current top.
+    pub fn can_alloc<T>(&self) -> bool {
+        self.fits(std::mem::size_of::<T>(), align_of::<T>())
+    }
+
+    fn fits(&self, size: usize, align: usize) -> bool {
+        let offset = unsafe { self.stack.as_ptr().add(self.top) }.align_offset(align);
+        self.len() - self.top >= size.saturating_add(offset)
+    }
+
+    /// Reserves `size` bytes aligned to `align`, including the padding needed
+    /// at the current top, and returns a pointer to the start.
+    fn bump(&mut self, size: usize, align: usize) -> anyhow::Result<*mut u8> {
+        unsafe {
+            let ptr = self.stack.as_mut_ptr().add(self.top).cast::<u8>();
+            let offset = ptr.align_offset(align);
+            if !self.fits(size, align) {
+                bail!("Stack allocator out of memory");
+            }
+            self.top += size + offset;
+            Ok(ptr.add(offset))
+        }
+    }
+
     pub fn clear(&mut self) {
+        self.run_drops_from(0);
+        self.allocs.clear();
         self.top = 0;
     }
 
-    pub fn popn(&mut self, n: usize) {
-        self.shrink(self.top - n);
+    /// Pops the top `n` bytes. Fails if fewer than `n` bytes are in use.
+    pub fn popn(&mut self, n: usize) -> anyhow::Result<()> {
+        let Some(to) = self.top.checked_sub(n) else {
+            bail!("StackAllocator::popn => Cannot pop more bytes than are allocated");
+        };
+        self.shrink(to)
     }
 
-    pub fn shrink(&mut self, to: usize) {
+    /// Moves the top down to `to`. Shrinking can only ever free space, so a
+    /// `to` above the current top is rejected.
+    pub fn shrink(&mut self, to: usize) -> anyhow::Result<()> {
+        if to > self.top || to > S {
+            bail!("StackAllocator::shrink => Target is above the current top");
+        }
+        self.run_drops_from(to);
+        while self.allocs.last().is_some_and(|e| e.offset + e.size > to) {
+            self.allocs.pop();
+        }
         self.top = to;
+        Ok(())
+    }
+}
+
+impl<const S: usize> Drop for StackAllocator<S> {
+    fn drop(&mut self) {
+        self.run_drops_from(0);
+    }
+}
+
+/// One-line summary for log output, e.g.
+/// `StackAllocator(used=96/1024 bytes, 9% full, 3 allocs)`.
+impl<const S: usize> fmt::Display for StackAllocator<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "StackAllocator(used={}/{} bytes, {}% full, {} allocs)",
+            self.top,
+            S,
+            percent(self.top, S),
+            self.allocs.len()
+        )
+    }
+}
+
+/// Whole-number percentage of `part` in `whole`, or 0 for an empty whole.
+fn percent(part: usize, whole: usize) -> usize {
+    match whole {
+        0 => 0,
+        _ => (part as u128 * 100 / whole as u128) as usize,
+    }
+}
+
+/// `StackAllocator` that can live in a `static`, built at compile time for
+/// targets without runtime init:
+/// `static ALLOC: StaticStack<1024> = StaticStack::new();`. Access goes
+/// through `with`, which panics rather than hand out a second `&mut` while
+/// one is live.
+///
+/// The allocator belongs to the first thread that calls `with`; any other
+/// thread panics. That keeps `!Send` values stored in it on the thread that
+/// stored them.
+pub struct StaticStack<const S: usize> {
+    stack: UnsafeCell<StackAllocator<S>>,
+    in_use: AtomicBool,
+    owner: OnceLock<ThreadId>,
+}
+
+// Only the owner thread ever reaches `stack`.
+unsafe impl<const S: usize> Sync for StaticStack<S> {}
+
+impl<const S: usize> Default for StaticStack<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const S: usize> StaticStack<S> {
+    pub const fn new() -> Self {
+        Self {
+            stack: UnsafeCell::new(StackAllocator::new()),
+            in_use: AtomicBool::new(false),
+            owner: OnceLock::new(),
+        }
+    }
+
+    /// Runs `f` with exclusive access to the allocator. References handed
+    /// out by the scope cannot outlive `f`.
+    ///
+    /// Panics if called again from within `f` or from a thread other than
+    /// the owner.
+    pub fn with<R>(&self, f: impl FnOnce(&mut StaticScope<'_, S>) -> R) -> R {
+        let me = std::thread::current().id();
+        if *self.owner.get_or_init(|| me) != me {
+            panic!("StaticStack::with => Accessed from a thread other than its owner");
+        }
+        if self.in_use.swap(true, Ordering::Acquire) {
+            panic!("StaticStack::with => Allocator is already in use");
+        }
+        struct Release<'a>(&'a AtomicBool);
+        impl Drop for Release<'_> {
+            fn drop(&mut self) {
+                self.0.store(false, Ordering::Release);
+            }
+        }
+        let _release = Release(&self.in_use);
+        f(&mut StaticScope(unsafe { &mut *self.stack.get() }))
+    }
+}
+
+/// Access to a `StaticStack`'s allocator for the duration of one `with`
+/// call. Allocations come back as references bound to the scope instead of
+/// `RadPtr`s, so nothing can point into the stack once `with` returns.
+pub struct StaticScope<'a, const S: usize>(&'a mut StackAllocator<S>);
+
+impl<const S: usize> StaticScope<'_, S> {
+    pub fn alloc<T>(&mut self, data: T) -> anyhow::Result<&mut T> {
+        let ptr = self.0.alloc(data)?;
+        Ok(unsafe { &mut *ptr.ptr })
+    }
+
+    /// See `StackAllocator::alloc_with_drop`.
+    pub fn alloc_with_drop<T>(&mut self, data: T) -> anyhow::Result<&mut T> {
+        let ptr = self.0.alloc_with_drop(data)?;
+        Ok(unsafe { &mut *ptr.ptr })
+    }
+
+    pub fn used(&self) -> usize {
+        self.0.used()
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear()
     }
 }
 
+/// `repr(C)` with the payload first: the payload sits at offset 0, so every
+/// cell in an array of them is aligned for `T` without further adjustment.
 #[derive(Debug)]
+#[repr(C)]
 struct PoolCell<T> {
     cell: T,
     slot: isize,
     next: isize,
     valid: bool,
+    generation: u32,
 }
 
+/// Handle to a pool cell, stamped with the cell's generation at the time of
+/// allocation. Equality and hashing use the cell's identity and that
+/// generation, never the `T` value, so a handle to a recycled slot never
+/// equals a stale one.
 #[derive(Debug)]
-pub struct PoolPtr<T>(RadPtr<PoolCell<T>>);
+pub struct PoolPtr<T>(RadPtr<PoolCell<T>>, u32);
 
 impl<T> Clone for PoolPtr<T> {
     fn clone(&self) -> Self {
-        Self(self.0.clone())
+        Self(self.0.clone(), self.1)
+    }
+}
+
+impl<T> PartialEq for PoolPtr<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.ptr == other.0.ptr && self.1 == other.1
+    }
+}
+
+impl<T> Eq for PoolPtr<T> {}
+
+impl<T> Hash for PoolPtr<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.ptr.hash(state);
+        self.1.hash(state);
     }
 }
 
@@ -120,202 +534,3898 @@ pub struct PoolAllocator<T> {
     layout: Layout,
     size: isize,
     next_available: isize,
+    monotonic: bool,
+    zeroing: bool,
+    /// Slot a round-robin pool starts searching from, or `None` for the
+    /// default LIFO free-list order.
+    round_robin: Option<isize>,
+    owned: bool,
 }
 
 impl<T> PoolAllocator<T> {
     pub fn new(size: isize) -> Self {
         unsafe {
-            let layout = Layout::array::<T>(size as usize).expect("Error with memory layout size");
-            let ptr = alloc(layout);
-            let ptr = ptr as *mut PoolCell<T>;
+            let layout = Self::buffer_layout(size as usize);
+            let ptr = alloc_zeroed(layout);
+            Self::init(ptr as *mut PoolCell<T>, layout, size, true)
+        }
+    }
 
-            for i in 0..size {
-                let cell = &mut *ptr.offset(i);
-                cell.next = i + 1;
-                cell.slot = i;
-                cell.valid = false;
-            }
+    /// Layout a caller-provided buffer must satisfy to hold `size` slots; see
+    /// `from_raw_parts`.
+    pub fn buffer_layout(size: usize) -> Layout {
+        Layout::array::<PoolCell<T>>(size).expect("Error with memory layout size")
+    }
 
-            let back = &mut *ptr.offset(size - 1);
-            back.next = -1;
+    /// Creates a pool of `size` slots over memory the caller manages, e.g. a
+    /// static array or a region carved from another arena. The pool never
+    /// touches the global allocator and does not free `buf` when dropped,
+    /// though it still drops any live values. `buf` is zeroed first.
+    ///
+    /// # Safety
+    ///
+    /// `buf` must be valid for reads and writes of `buffer_layout(size).size()`
+    /// bytes, aligned to `buffer_layout(size).align()`, not used for anything
+    /// else, and outlive the pool and every `PoolPtr` it hands out.
+    pub unsafe fn from_raw_parts(buf: NonNull<u8>, size: usize) -> Self {
+        let layout = Self::buffer_layout(size);
+        debug_assert!(
+            buf.as_ptr().align_offset(layout.align()) == 0,
+            "PoolAllocator::from_raw_parts => Buffer is misaligned for the pool's cells"
+        );
+        std::ptr::write_bytes(buf.as_ptr(), 0, layout.size());
+        Self::init(buf.as_ptr().cast(), layout, size as isize, false)
+    }
 
-            Self {
-                buf: ptr,
-                layout,
-                size,
-                next_available: 0,
-            }
+    unsafe fn init(ptr: *mut PoolCell<T>, layout: Layout, size: isize, owned: bool) -> Self {
+        for i in 0..size {
+            let cell = ptr.offset(i);
+            (*cell).next = i + 1;
+            (*cell).slot = i;
+            (*cell).valid = false;
+            (*cell).generation = 0;
+        }
+
+        if size > 0 {
+            let back = ptr.offset(size - 1);
+            (*back).next = -1;
         }
-    }
 
-    pub fn alloc(&mut self, data: T) -> PoolPtr<T> {
-        let next_avail = self.next_available;
-        let c = self.at_mut(next_avail);
-        c.cell = data;
-        self.next_available = c.next;
-        self.at_ptr(next_avail)
+        Self {
+            buf: ptr,
+            layout,
+            size,
+            next_available: if size > 0 { 0 } else { -1 },
+            monotonic: false,
+            zeroing: false,
+            round_robin: None,
+            owned,
+        }
     }
 
-    pub fn dealloc(&mut self, ptr: PoolPtr<T>) {
-        let mut ptr = ptr.clone();
-        let cell = ptr.pcell_mut();
-        cell.next = self.next_available;
-        self.next_available = cell.slot;
+    /// Creates a pool that never hands out the same slot twice: deallocated
+    /// slots are retired rather than returned to the free list, so a stale
+    /// `PoolPtr` can never alias a newer object. Once every slot has been
+    /// used, `alloc` fails.
+    pub fn new_monotonic(size: isize) -> Self {
+        let mut pool = Self::new(size);
+        pool.monotonic = true;
+        pool
     }
 
-    fn at(&self, slot: isize) -> &PoolCell<T> {
-        unsafe {
-            let ptr = self.buf.offset(slot);
-            &*ptr
-        }
+    /// Creates a pool that wipes each cell's payload before it is handed out
+    /// and again after its value is dropped, so data left by a previous
+    /// occupant never leaks to the next.
+    pub fn new_zeroing(size: isize) -> Self {
+        let mut pool = Self::new(size);
+        pool.zeroing = true;
+        pool
     }
 
-    fn at_mut(&mut self, slot: isize) -> &mut PoolCell<T> {
-        unsafe {
-            let ptr = self.buf.offset(slot);
-            let offset = ptr.align_offset(align_of::<T>());
-            let ptr = ptr.add(offset);
-            &mut *ptr
-        }
+    /// Creates a pool that hands out slots in round-robin order: each `alloc`
+    /// takes the first free slot after the one allocated last, wrapping
+    /// around, instead of reusing the most recently freed one. This spreads
+    /// use evenly across all slots at the cost of an O(size) `alloc`.
+    pub fn new_round_robin(size: isize) -> Self {
+        let mut pool = Self::new(size);
+        pool.round_robin = Some(0);
+        pool
     }
 
-    fn at_ptr(&self, slot: isize) -> PoolPtr<T> {
+    pub fn alloc(&mut self, data: T) -> anyhow::Result<PoolPtr<T>> {
+        let next_avail = match self.round_robin {
+            Some(cursor) => (0..self.size)
+                .map(|i| (cursor + i) % self.size)
+                .find(|&slot| !self.at(slot).valid)
+                .unwrap_or(-1),
+            None => self.next_available,
+        };
+        if next_avail < 0 || next_avail >= self.size {
+            bail!("PoolAllocator::alloc => Cannot perform allocation: Pool out of slots");
+        }
+        // The free cell's payload is dead, so go through raw pointers rather
+        // than a `&mut PoolCell<T>`.
         unsafe {
-            let ptr = self.buf.offset(slot);
-            PoolPtr(RadPtr { ptr })
+            let c = self.buf.offset(next_avail);
+            let payload = std::ptr::addr_of_mut!((*c).cell);
+            if self.zeroing {
+                zero_payload(payload);
+            }
+            std::ptr::write(payload, data);
+            (*c).valid = true;
         }
+        match self.round_robin {
+            Some(_) => self.round_robin = Some((next_avail + 1) % self.size),
+            None => self.next_available = self.at(next_avail).next,
+        }
+        Ok(self.at_ptr(next_avail))
     }
-}
 
-impl<T> PoolPtr<T> {
-    fn pcell(&self) -> &PoolCell<T> {
-        &self.0
+    pub fn dealloc(&mut self, ptr: PoolPtr<T>) {
+        let Some(slot) = self.live_slot(&ptr) else {
+            return;
+        };
+        self.retire(slot);
+        if self.monotonic || self.round_robin.is_some() {
+            return;
+        }
+        let next_available = self.next_available;
+        self.at_mut(slot).next = next_available;
+        self.next_available = slot;
     }
 
-    fn pcell_mut(&mut self) -> &mut PoolCell<T> {
-        &mut self.0
+    /// Frees every live cell except those in `keep`, which stay valid at
+    /// their current addresses. The free list is rebuilt in slot order.
+    pub fn reset_except(&mut self, keep: &[PoolPtr<T>]) {
+        let mut kept = vec![false; self.size.max(0) as usize];
+        for slot in keep.iter().filter_map(|p| self.live_slot(p)) {
+            kept[slot as usize] = true;
+        }
+        for slot in 0..self.size {
+            if self.at(slot).valid && !kept[slot as usize] {
+                self.retire(slot);
+            }
+        }
+        if !self.monotonic {
+            self.rebuild_free_list();
+        }
     }
-}
 
-impl<T> Deref for PoolPtr<T> {
-    fn deref(&self) -> &Self::Target {
-        &self.0.cell
+    /// Moves every live value into `dst`, in slot order, and returns a map
+    /// from each value's old slot to its new one. Values are moved, not
+    /// cloned or dropped. If `dst` runs out of slots, the values that did not
+    /// fit stay live in `self` and are absent from the map.
+    pub fn migrate_into(&mut self, dst: &mut PoolAllocator<T>) -> HashMap<isize, isize> {
+        let mut remap = HashMap::new();
+        for slot in 0..self.size {
+            if !self.at(slot).valid {
+                continue;
+            }
+            if !dst.has_free_slot() {
+                break;
+            }
+            let value = unsafe { std::ptr::read(&self.at(slot).cell) };
+            self.vacate(slot);
+            let moved = dst
+                .alloc(value)
+                .expect("PoolAllocator::migrate_into => Destination has a free slot");
+            remap.insert(slot, moved.slot());
+        }
+        if !self.monotonic {
+            self.rebuild_free_list();
+        }
+        remap
     }
 
-    type Target = T;
-}
+    /// Merges `other`, a pool over the slots directly following this pool's
+    /// in the same caller-provided buffer, into this one. Its cells are
+    /// renumbered to follow this pool's and its free list is appended to this
+    /// pool's, so existing handles into either pool stay valid.
+    ///
+    /// Both pools must come from `from_raw_parts`, since an owned buffer
+    /// cannot be resized in place, and must share the same mode.
+    pub fn absorb(&mut self, other: PoolAllocator<T>) -> anyhow::Result<()> {
+        if self.owned || other.owned {
+            bail!("PoolAllocator::absorb => Only pools over caller-provided buffers can be merged");
+        }
+        if other.buf != self.buf.wrapping_offset(self.size) {
+            bail!("PoolAllocator::absorb => Pools are not adjacent in memory");
+        }
+        if (self.monotonic, self.zeroing, self.round_robin.is_some())
+            != (other.monotonic, other.zeroing, other.round_robin.is_some())
+        {
+            bail!("PoolAllocator::absorb => Pools use different modes");
+        }
+        let mut other = ManuallyDrop::new(other);
+        let base = self.size;
+        for slot in 0..other.size {
+            let cell = other.at_mut(slot);
+            cell.slot += base;
+            if cell.next >= 0 {
+                cell.next += base;
+            }
+        }
+        let other_head = match other.next_available {
+            next if next >= 0 && next < other.size => next + base,
+            _ => -1,
+        };
 
-impl<T> DerefMut for PoolPtr<T> {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0.cell
+        let mut tail = None;
+        let mut next = self.next_available;
+        while next >= 0 && next < self.size {
+            tail = Some(next);
+            next = self.at(next).next;
+        }
+        match tail {
+            Some(slot) => self.at_mut(slot).next = other_head,
+            None => self.next_available = other_head,
+        }
+        self.size += other.size;
+        self.layout = Self::buffer_layout(self.size as usize);
+        Ok(())
     }
-}
 
-impl<T> Drop for PoolAllocator<T> {
-    fn drop(&mut self) {
-        unsafe { dealloc(self.buf as *mut u8, self.layout) }
+    /// Calls `f` on every live value, in slot order, just before it is
+    /// dropped, then frees all cells and rebuilds the free list.
+    pub fn reset_with(&mut self, mut f: impl FnMut(&mut T)) {
+        for slot in 0..self.size {
+            if self.at(slot).valid {
+                f(&mut self.at_mut(slot).cell);
+                self.retire(slot);
+            }
+        }
+        if !self.monotonic {
+            self.rebuild_free_list();
+        }
     }
-}
 
-pub type BumpPtr<T> = RadPtr<T>;
+    /// Compact id of the slot `ptr` refers to, for external sparse-set
+    /// storage. The id carries no generation: once the slot is freed and
+    /// reused it resolves to the new occupant, whose `PoolPtr` compares
+    /// unequal to the old one.
+    ///
+    /// Panics if the slot index doesn't fit in a `u32`.
+    pub fn slot_id(&self, ptr: &PoolPtr<T>) -> u32 {
+        u32::try_from(ptr.slot()).expect("PoolAllocator::slot_id => Slot index exceeds u32")
+    }
 
-pub struct BumpAllocator {
-    buf: *mut u8,
+    /// Rebuilds a pointer from a `slot_id`, stamped with the slot's current
+    /// generation, or `None` if the slot is out of range or free.
+    pub fn ptr_from_slot_id(&self, id: u32) -> Option<PoolPtr<T>> {
+        let slot = isize::try_from(id).ok()?;
+        (slot < self.size && self.at(slot).valid).then(|| self.at_ptr(slot))
+    }
 
-    layout: Layout,
-    capacity: usize,
-    size: usize,
-}
+    /// Whether `alloc` would find a free slot right now.
+    fn has_free_slot(&self) -> bool {
+        match self.round_robin {
+            Some(_) => (0..self.size).any(|slot| !self.at(slot).valid),
+            None => self.next_available >= 0 && self.next_available < self.size,
+        }
+    }
 
-impl BumpAllocator {
-    pub const DEFAULT_ALIGNMENT: usize = std::mem::align_of::<u8>();
+    /// Slot that `ptr` refers to, if it belongs to this pool and the cell is
+    /// still the allocation it was handed out for.
+    fn live_slot(&self, ptr: &PoolPtr<T>) -> Option<isize> {
+        let offset = (ptr.0.ptr as usize).checked_sub(self.buf as usize)?;
+        let slot = (offset / std::mem::size_of::<PoolCell<T>>().max(1)) as isize;
+        if slot >= self.size || self.at(slot).generation != ptr.1 || !self.at(slot).valid {
+            return None;
+        }
+        Some(slot)
+    }
 
-    pub fn new(size_bytes: usize) -> anyhow::Result<Self> {
-        Self::with_align(size_bytes, Self::DEFAULT_ALIGNMENT)
+    /// Drops the value in a live cell and marks it invalid, bumping its
+    /// generation. Does not touch the free list.
+    fn retire(&mut self, slot: isize) {
+        unsafe { std::ptr::drop_in_place(&mut self.at_mut(slot).cell) };
+        self.vacate(slot);
     }
 
-    pub fn with_align(size_bytes: usize, align: usize) -> anyhow::Result<Self> {
+    /// Marks a live cell invalid without dropping its value, which must
+    /// already have been dropped or moved out.
+    fn vacate(&mut self, slot: isize) {
         unsafe {
-            let layout = Layout::from_size_align(size_bytes, align)?;
-            let buf = alloc(layout);
-            if buf.is_null() {
-                bail!("BumpAllocator::with_align => Unable to allocate more memory from Global Allocator");
+            let cell = self.buf.offset(slot);
+            if self.zeroing {
+                zero_payload(std::ptr::addr_of_mut!((*cell).cell));
             }
-            let top = buf;
-            let capacity = size_bytes;
-
-            let s = Self {
-                buf,
-                layout,
-                capacity,
-
-                size: 0,
-            };
-            Ok(s)
+            (*cell).valid = false;
+            (*cell).generation = (*cell).generation.wrapping_add(1);
         }
     }
 
-    pub fn alloc<T>(&mut self, data: T) -> anyhow::Result<BumpPtr<T>> {
-        unsafe {
-            let data_size = std::mem::size_of::<T>();
-            if self.size + data_size > self.capacity {
-                bail!(
-                    "BumpAllocator::alloc => Cannot performa allocation: Allocator out of memory"
-                );
+    /// Links every invalid cell into a fresh free list in slot order and
+    /// points `next_available` at its head, e.g. after cell bytes have been
+    /// restored from outside the pool. Each cell's recorded slot index is
+    /// reset to its position too. The live count needs no fixing up, since
+    /// it is always derived from the cells' valid flags.
+    pub fn rebuild_free_list(&mut self) {
+        let mut head = -1;
+        for slot in (0..self.size).rev() {
+            let cell = self.at_mut(slot);
+            cell.slot = slot;
+            if !cell.valid {
+                cell.next = head;
+                head = slot;
             }
-
-            let ptr = self.buf.add(self.size);
-            let offset = ptr.align_offset(align_of::<T>());
-            let ptr = ptr.add(offset).cast::<T>();
-            std::ptr::write(ptr, data);
-            self.size += data_size + offset;
-
-            let sp = RadPtr { ptr };
-            Ok(sp)
         }
+        self.next_available = head;
     }
 
-    pub fn clear(&mut self) {
-        self.size = 0;
+    /// Checks that the cell metadata is consistent: every cell records its
+    /// own slot index, and the free list only holds distinct invalid cells.
+    /// Outside monotonic mode, where freed cells are never relinked, the free
+    /// list must also hold every invalid cell. Round-robin pools keep no free
+    /// list, so only the slot indices are checked.
+    pub fn check_invariants(&self) -> anyhow::Result<()> {
+        if let Some(slot) = (0..self.size).find(|&slot| self.at(slot).slot != slot) {
+            bail!("PoolAllocator::check_invariants => Cell {slot} records the wrong slot index");
+        }
+        if self.round_robin.is_some() {
+            return Ok(());
+        }
+        let mut listed = vec![false; self.size.max(0) as usize];
+        let mut next = self.next_available;
+        while next >= 0 && next < self.size {
+            if listed[next as usize] {
+                bail!("PoolAllocator::check_invariants => Free list revisits slot {next}");
+            }
+            if self.at(next).valid {
+                bail!("PoolAllocator::check_invariants => Free list holds live slot {next}");
+            }
+            listed[next as usize] = true;
+            next = self.at(next).next;
+        }
+        if next >= 0 && next != self.size {
+            bail!("PoolAllocator::check_invariants => Free list links out of bounds to {next}");
+        }
+        if !self.monotonic {
+            if let Some(slot) = (0..self.size).find(|&s| !self.at(s).valid && !listed[s as usize]) {
+                bail!("PoolAllocator::check_invariants => Free slot {slot} is missing from the free list");
+            }
+        }
+        Ok(())
     }
 
-    pub fn release(self) {
-        drop(self)
+    /// Walks the free list, yielding slot indices in the order `alloc` will
+    /// hand them out. Stops after `size` steps in case the list is cyclic.
+    /// Round-robin pools keep no list; their free slots are found by scanning
+    /// from the cursor instead.
+    pub fn free_slots(&self) -> impl Iterator<Item = isize> + '_ {
+        let scan = self.round_robin.map(|cursor| {
+            (0..self.size)
+                .map(move |i| (cursor + i) % self.size)
+                .filter(|&slot| !self.at(slot).valid)
+        });
+        let mut next = match self.round_robin {
+            Some(_) => -1,
+            None => self.next_available,
+        };
+        let mut remaining = self.size;
+        scan.into_iter()
+            .flatten()
+            .chain(std::iter::from_fn(move || {
+                if next < 0 || next >= self.size || remaining == 0 {
+                    return None;
+                }
+                remaining -= 1;
+                let slot = next;
+                next = self.at(slot).next;
+                Some(slot)
+            }))
     }
-}
 
-impl Drop for BumpAllocator {
-    fn drop(&mut self) {
-        unsafe { dealloc(self.buf as *mut u8, self.layout) }
+    /// Iterates the values of all live cells in slot order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        (0..self.size)
+            .map(|slot| self.at(slot))
+            .filter(|cell| cell.valid)
+            .map(|cell| &cell.cell)
     }
-}
-
-pub struct DoubleBumpAllocator {
-    bufs: [BumpAllocator; 2],
-    current: usize,
-}
 
-impl DoubleBumpAllocator {
-    pub fn new(size_bytes: usize) -> anyhow::Result<Self> {
-        Self::with_align(size_bytes, BumpAllocator::DEFAULT_ALIGNMENT)
+    /// Live values and free-list slot indices together, as `iter` and
+    /// `free_slots` would yield them, for a full picture of occupancy.
+    pub fn partition(
+        &self,
+    ) -> (
+        impl Iterator<Item = &T> + '_,
+        impl Iterator<Item = isize> + '_,
+    ) {
+        (self.iter(), self.free_slots())
     }
 
-    pub fn with_align(size_bytes: usize, align: usize) -> anyhow::Result<Self> {
-        let a = BumpAllocator::with_align(size_bytes, align)?;
-        let b = BumpAllocator::with_align(size_bytes, align)?;
+    pub fn backing(&self) -> Backing {
+        if self.owned {
+            Backing::Heap
+        } else {
+            Backing::BorrowedSlice
+        }
+    }
 
-        let s = Self {
-            bufs: [a, b],
-            current: 0,
-        };
-        Ok(s)
+    /// Consumes the pool into a read-only view that can be shared across
+    /// threads. The slots keep their addresses and live values.
+    pub fn freeze(self) -> FrozenPool<T> {
+        FrozenPool { pool: self }
     }
 
-    pub fn swap(&mut self) {
-        self.current = !self.current;
+    /// Folds `f` over the values of all live cells in slot order.
+    pub fn fold<B>(&self, init: B, f: impl FnMut(B, &T) -> B) -> B {
+        self.iter().fold(init, f)
     }
 
-    pub fn current(&self) -> &BumpAllocator {
+    /// Sum of the values of all live cells.
+    pub fn sum(&self) -> T
+    where
+        T: std::ops::Add<Output = T> + Default + Copy,
+    {
+        self.fold(T::default(), |acc, &v| acc + v)
+    }
+
+    fn at(&self, slot: isize) -> &PoolCell<T> {
+        unsafe { &*self.buf.offset(slot) }
+    }
+
+    fn at_mut(&mut self, slot: isize) -> &mut PoolCell<T> {
+        unsafe { &mut *self.buf.offset(slot) }
+    }
+
+    fn at_ptr(&self, slot: isize) -> PoolPtr<T> {
+        unsafe {
+            let ptr = self.buf.offset(slot);
+            PoolPtr(RadPtr { ptr }, (*ptr).generation)
+        }
+    }
+}
+
+/// Pool state that lives outside the backing buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolHeader {
+    pub size: isize,
+    pub next_available: isize,
+    pub live: usize,
+}
+
+/// Byte-for-byte copy of a pool's buffer plus its header; see
+/// `PoolAllocator::snapshot`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolSnapshot {
+    pub header: PoolHeader,
+    pub bytes: Vec<u8>,
+}
+
+/// Snapshotting copies cells bytewise, so it is limited to POD values. The
+/// buffer starts zeroed and a `Pod` payload has no padding, so every byte of
+/// it is initialized.
+#[cfg(feature = "bytemuck")]
+impl<T: bytemuck::Pod> PoolAllocator<T> {
+    /// The whole backing buffer, cell metadata included.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.buf.cast::<u8>(), self.layout.size()) }
+    }
+
+    /// FNV-1a hash of the payload bytes of every live cell, in slot order.
+    /// Free cells, generations and the free list are left out, so pools
+    /// holding the same values in the same slots agree whatever their
+    /// allocation history.
+    pub fn live_checksum(&self) -> u64 {
+        self.iter().fold(fnv1a(&[]), |hash, value| {
+            let bytes = unsafe {
+                std::slice::from_raw_parts(
+                    (value as *const T).cast::<u8>(),
+                    std::mem::size_of::<T>(),
+                )
+            };
+            fnv1a_extend(hash, bytes)
+        })
+    }
+
+    pub fn header(&self) -> PoolHeader {
+        PoolHeader {
+            size: self.size,
+            next_available: self.next_available,
+            live: self.iter().count(),
+        }
+    }
+
+    pub fn snapshot(&self) -> PoolSnapshot {
+        PoolSnapshot {
+            header: self.header(),
+            bytes: self.as_bytes().to_vec(),
+        }
+    }
+
+    /// Restores the exact state captured by `snapshot`, including the free
+    /// list. Fails if the snapshot was taken from a pool of a different size.
+    ///
+    /// # Safety
+    /// `snapshot` must have come from `snapshot` on a pool of the same `T`
+    /// and size, unmodified. The cell metadata is copied without checking,
+    /// so hand-built bytes can leave invalid flags or out-of-range slots.
+    pub unsafe fn restore(&mut self, snapshot: &PoolSnapshot) -> anyhow::Result<()> {
+        if snapshot.header.size != self.size || snapshot.bytes.len() != self.layout.size() {
+            bail!("PoolAllocator::restore => Snapshot does not match this pool's size");
+        }
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                snapshot.bytes.as_ptr(),
+                self.buf.cast::<u8>(),
+                snapshot.bytes.len(),
+            )
+        };
+        self.next_available = snapshot.header.next_available;
+        Ok(())
+    }
+}
+
+/// One `PoolAllocator` per type behind a single handle, each created on first
+/// use with `slots_per_pool` slots, so many small objects of a handful of
+/// types each get a fragmentation-free pool.
+pub struct SlabRegistry {
+    pools: HashMap<TypeId, Box<dyn Any>>,
+    slots_per_pool: isize,
+}
+
+impl SlabRegistry {
+    pub fn new(slots_per_pool: isize) -> Self {
+        Self {
+            pools: HashMap::new(),
+            slots_per_pool,
+        }
+    }
+
+    /// Allocates `data` in the pool for `T`, creating that pool if needed.
+    pub fn alloc<T: 'static>(&mut self, data: T) -> anyhow::Result<PoolPtr<T>> {
+        let slots = self.slots_per_pool;
+        self.pools
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(PoolAllocator::<T>::new(slots)))
+            .downcast_mut::<PoolAllocator<T>>()
+            .expect("SlabRegistry::alloc => Pool is keyed by its own TypeId")
+            .alloc(data)
+    }
+
+    /// Makes sure the pool for `T` has at least `count` free slots, creating
+    /// it up front if needed, so the next `count` allocations of `T` never
+    /// touch the global allocator. Pools can't grow in place, so an existing
+    /// pool that is too small is only replaced while it holds no live values.
+    pub fn reserve<T: 'static>(&mut self, count: usize) -> anyhow::Result<()> {
+        let count = isize::try_from(count)?;
+        if let Some(pool) = self.pool::<T>() {
+            if pool.free_slots().count() as isize >= count {
+                return Ok(());
+            }
+            if pool.iter().next().is_some() {
+                bail!("SlabRegistry::reserve => Cannot grow a pool that holds live values");
+            }
+        }
+        let pool = PoolAllocator::<T>::new(count.max(self.slots_per_pool));
+        self.pools.insert(TypeId::of::<T>(), Box::new(pool));
+        Ok(())
+    }
+
+    /// The pool for `T`, if anything of that type has been allocated.
+    pub fn pool<T: 'static>(&self) -> Option<&PoolAllocator<T>> {
+        self.pools.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    pub fn pool_mut<T: 'static>(&mut self) -> Option<&mut PoolAllocator<T>> {
+        self.pools.get_mut(&TypeId::of::<T>())?.downcast_mut()
+    }
+
+    /// Number of per-type pools created so far.
+    pub fn pool_count(&self) -> usize {
+        self.pools.len()
+    }
+}
+
+/// Moves the value behind `bump_ptr` out of `bump` into `pool`, e.g. to keep
+/// a long-lived object built in a per-frame arena. Returns `None`, leaving
+/// the bump value untouched, if the pool has no free slot. Any destructor
+/// registered with the arena moves with the value, as in `move_out`.
+///
+/// # Safety
+/// Same as `BumpAllocator::move_out`: `bump_ptr` must point to a live `T`
+/// allocated from `bump`, and after a successful move neither it nor any copy
+/// of it may be used.
+pub unsafe fn promote_to_pool<T>(
+    bump: &mut BumpAllocator,
+    bump_ptr: BumpPtr<T>,
+    pool: &mut PoolAllocator<T>,
+) -> Option<PoolPtr<T>> {
+    if !pool.has_free_slot() {
+        return None;
+    }
+    let value = bump.move_out(bump_ptr);
+    Some(
+        pool.alloc(value)
+            .expect("promote_to_pool => Pool has a free slot"),
+    )
+}
+
+/// 64-bit FNV-1a, used where a hash must be stable across runs.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    fnv1a_extend(0xcbf2_9ce4_8422_2325, bytes)
+}
+
+/// Continues an FNV-1a `hash` over more `bytes`.
+fn fnv1a_extend(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Overwrites the bytes of `payload` with zeros using volatile writes so the
+/// wipe can't be optimized away.
+fn zero_payload<T>(payload: *mut T) {
+    zero_bytes(payload.cast::<u8>(), std::mem::size_of::<T>());
+}
+
+fn zero_bytes(bytes: *mut u8, len: usize) {
+    for i in 0..len {
+        unsafe { std::ptr::write_volatile(bytes.add(i), 0) };
+    }
+    std::sync::atomic::compiler_fence(Ordering::SeqCst);
+}
+
+impl<T> PoolPtr<T> {
+    /// Index of the pool slot this pointer refers to.
+    pub fn slot(&self) -> isize {
+        self.0.slot
+    }
+
+    /// Generation of the slot when this pointer was handed out.
+    pub fn generation(&self) -> u32 {
+        self.1
+    }
+}
+
+impl<T> Deref for PoolPtr<T> {
+    fn deref(&self) -> &Self::Target {
+        &self.0.cell
+    }
+
+    type Target = T;
+}
+
+impl<T> DerefMut for PoolPtr<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0.cell
+    }
+}
+
+impl<T> Drop for PoolAllocator<T> {
+    fn drop(&mut self) {
+        for i in 0..self.size {
+            let cell = self.at_mut(i);
+            if cell.valid {
+                unsafe { std::ptr::drop_in_place(&mut cell.cell) };
+            }
+        }
+        if self.owned {
+            unsafe { dealloc(self.buf as *mut u8, self.layout) }
+        }
+    }
+}
+
+/// One-line summary for log output, e.g.
+/// `PoolAllocator(live=12/64 slots, 18% full)`.
+impl<T> fmt::Display for PoolAllocator<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let size = self.size.max(0) as usize;
+        let live = self.iter().count();
+        write!(
+            f,
+            "PoolAllocator(live={live}/{size} slots, {}% full)",
+            percent(live, size)
+        )
+    }
+}
+
+/// Read-only view of a `PoolAllocator` that no longer allocates or frees,
+/// for parallel reads over a pool in steady state. The values are dropped
+/// and the buffer freed when the view is dropped.
+///
+/// Any `PoolPtr` still held from before freezing must not be used to mutate
+/// its value while the view is shared.
+pub struct FrozenPool<T> {
+    pool: PoolAllocator<T>,
+}
+
+// SAFETY: A frozen pool only hands out shared references to its values.
+unsafe impl<T: Sync> Sync for FrozenPool<T> {}
+unsafe impl<T: Send> Send for FrozenPool<T> {}
+
+impl<T> FrozenPool<T> {
+    /// Iterates the values of all live cells in slot order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        self.pool.iter()
+    }
+
+    /// The value in `slot`, if that slot holds a live cell.
+    pub fn get(&self, slot: isize) -> Option<&T> {
+        if !(0..self.pool.size).contains(&slot) {
+            return None;
+        }
+        let cell = self.pool.at(slot);
+        cell.valid.then_some(&cell.cell)
+    }
+
+    /// Number of slots in the pool, live or not.
+    pub fn len(&self) -> isize {
+        self.pool.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pool.size == 0
+    }
+}
+
+/// Free-list bookkeeping for one `SoaPoolAllocator` slot.
+#[derive(Debug, Clone, Copy)]
+struct SoaMeta {
+    next: isize,
+    valid: bool,
+    generation: u32,
+}
+
+/// Handle to a value in a `SoaPoolAllocator`.
+#[derive(Debug)]
+pub struct SoaPoolPtr<T> {
+    ptr: *mut T,
+    slot: isize,
+    generation: u32,
+}
+
+impl<T> Clone for SoaPoolPtr<T> {
+    fn clone(&self) -> Self {
+        Self {
+            ptr: self.ptr,
+            slot: self.slot,
+            generation: self.generation,
+        }
+    }
+}
+
+impl<T> SoaPoolPtr<T> {
+    pub fn slot(&self) -> isize {
+        self.slot
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+impl<T> Deref for SoaPoolPtr<T> {
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.ptr }
+    }
+
+    type Target = T;
+}
+
+impl<T> DerefMut for SoaPoolPtr<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.ptr }
+    }
+}
+
+/// Pool with the same semantics as `PoolAllocator`, but storing payloads in
+/// one dense array and the free-list metadata in a parallel one, so iterating
+/// values doesn't drag the bookkeeping through the cache.
+pub struct SoaPoolAllocator<T> {
+    payloads: *mut T,
+    layout: Layout,
+    meta: Vec<SoaMeta>,
+    next_available: isize,
+}
+
+impl<T> SoaPoolAllocator<T> {
+    pub fn new(size: isize) -> Self {
+        let layout = Layout::array::<T>(size as usize).expect("Error with memory layout size");
+        let payloads = if layout.size() == 0 {
+            NonNull::<T>::dangling().as_ptr()
+        } else {
+            let ptr = unsafe { alloc(layout) };
+            if ptr.is_null() {
+                handle_alloc_error(layout);
+            }
+            ptr.cast::<T>()
+        };
+        let meta = (0..size)
+            .map(|i| SoaMeta {
+                next: if i + 1 < size { i + 1 } else { -1 },
+                valid: false,
+                generation: 0,
+            })
+            .collect();
+
+        Self {
+            payloads,
+            layout,
+            meta,
+            next_available: if size > 0 { 0 } else { -1 },
+        }
+    }
+
+    pub fn backing(&self) -> Backing {
+        Backing::Heap
+    }
+
+    pub fn alloc(&mut self, data: T) -> anyhow::Result<SoaPoolPtr<T>> {
+        let slot = self.next_available;
+        if slot < 0 {
+            bail!("SoaPoolAllocator::alloc => Cannot perform allocation: Pool out of slots");
+        }
+        let meta = &mut self.meta[slot as usize];
+        let ptr = unsafe { self.payloads.offset(slot) };
+        unsafe { std::ptr::write(ptr, data) };
+        meta.valid = true;
+        self.next_available = meta.next;
+
+        Ok(SoaPoolPtr {
+            ptr,
+            slot,
+            generation: meta.generation,
+        })
+    }
+
+    pub fn dealloc(&mut self, ptr: SoaPoolPtr<T>) {
+        let Some(meta) = self.meta.get_mut(ptr.slot as usize) else {
+            return;
+        };
+        if !meta.valid || meta.generation != ptr.generation {
+            return;
+        }
+        unsafe { std::ptr::drop_in_place(self.payloads.offset(ptr.slot)) };
+        meta.valid = false;
+        meta.generation = meta.generation.wrapping_add(1);
+        meta.next = self.next_available;
+        self.next_available = ptr.slot;
+    }
+
+    /// Iterates the values of all live slots in slot order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        self.meta
+            .iter()
+            .enumerate()
+            .filter(|(_, meta)| meta.valid)
+            .map(|(slot, _)| unsafe { &*self.payloads.add(slot) })
+    }
+}
+
+/// Viewed as bytes, so limited to `Pod` payloads, which have no padding.
+#[cfg(feature = "bytemuck")]
+impl<T: bytemuck::Pod> SoaPoolAllocator<T> {
+    /// The payload bytes of slots `start_slot..start_slot + count`, if every
+    /// one of them is live, e.g. to hand adjacent buffers to the kernel in a
+    /// single zero-copy send. Only this pool's payloads are stored back to
+    /// back; a `PoolAllocator` interleaves them with free-list metadata.
+    pub fn contiguous_live_range(&self, start_slot: isize, count: usize) -> Option<&[u8]> {
+        let start = usize::try_from(start_slot).ok()?;
+        let cells = self.meta.get(start..start.checked_add(count)?)?;
+        if !cells.iter().all(|meta| meta.valid) {
+            return None;
+        }
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                self.payloads.add(start).cast::<u8>(),
+                count * std::mem::size_of::<T>(),
+            )
+        };
+        Some(bytes)
+    }
+}
+
+impl<T> Drop for SoaPoolAllocator<T> {
+    fn drop(&mut self) {
+        for (slot, meta) in self.meta.iter().enumerate() {
+            if meta.valid {
+                unsafe { std::ptr::drop_in_place(self.payloads.add(slot)) };
+            }
+        }
+        if self.layout.size() != 0 {
+            unsafe { dealloc(self.payloads.cast(), self.layout) }
+        }
+    }
+}
+
+/// Views bytes from `BumpAllocator::alloc_uninit_bytes` as initialized.
+///
+/// # Safety
+///
+/// Every byte of `bytes` must have been written.
+pub unsafe fn assume_init_bytes(bytes: &[MaybeUninit<u8>]) -> &[u8] {
+    std::slice::from_raw_parts(bytes.as_ptr().cast::<u8>(), bytes.len())
+}
+
+/// A free byte range inside a `FreeListAllocator`'s buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FreeBlock {
+    offset: usize,
+    size: usize,
+}
+
+/// Variable-size allocator over a single buffer. Freed ranges go back onto
+/// an offset-sorted free list and are merged with their neighbours, and
+/// allocations are served first-fit.
+pub struct FreeListAllocator {
+    buf: *mut u8,
+    layout: Layout,
+    free: Vec<FreeBlock>,
+    min_block: usize,
+}
+
+impl FreeListAllocator {
+    pub const DEFAULT_ALIGNMENT: usize = 8;
+
+    pub fn new(size_bytes: usize) -> anyhow::Result<Self> {
+        Self::with_min_block(size_bytes, 1)
+    }
+
+    /// Creates an allocator that rounds every request up to at least
+    /// `min_block` bytes, trading internal waste for fewer tiny free blocks.
+    pub fn with_min_block(size_bytes: usize, min_block: usize) -> anyhow::Result<Self> {
+        unsafe {
+            let layout = Layout::from_size_align(size_bytes, Self::DEFAULT_ALIGNMENT)?;
+            let buf = alloc(layout);
+            if buf.is_null() {
+                bail!("FreeListAllocator::with_min_block => Unable to allocate more memory from Global Allocator");
+            }
+            let s = Self {
+                buf,
+                layout,
+                free: vec![FreeBlock {
+                    offset: 0,
+                    size: size_bytes,
+                }],
+                min_block: min_block.max(1),
+            };
+            Ok(s)
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.layout.size()
+    }
+
+    pub fn min_block(&self) -> usize {
+        self.min_block
+    }
+
+    pub fn backing(&self) -> Backing {
+        Backing::Heap
+    }
+
+    /// Bytes actually reserved for a request of `layout`.
+    fn block_size(&self, layout: Layout) -> usize {
+        layout.size().max(self.min_block)
+    }
+
+    /// Total bytes currently on the free list.
+    pub fn free_bytes(&self) -> usize {
+        self.free.iter().map(|b| b.size).sum()
+    }
+
+    /// Size of the largest contiguous free range.
+    pub fn largest_free_block(&self) -> usize {
+        self.free.iter().map(|b| b.size).max().unwrap_or(0)
+    }
+
+    /// `1 - largest_free_block / free_bytes`: 0 when all free space is one
+    /// contiguous range, approaching 1 as it scatters into small blocks.
+    /// A completely full allocator reports 0.
+    pub fn fragmentation(&self) -> f32 {
+        let total = self.free_bytes();
+        if total == 0 {
+            return 0.0;
+        }
+        1.0 - self.largest_free_block() as f32 / total as f32
+    }
+
+    pub fn alloc<T>(&mut self, data: T) -> anyhow::Result<RadPtr<T>> {
+        let ptr = self.alloc_layout(Layout::new::<T>())?.as_ptr().cast::<T>();
+        unsafe { std::ptr::write(ptr, data) };
+        Ok(RadPtr { ptr })
+    }
+
+    /// Reserves uninitialized memory for `layout` from the first free block
+    /// that can hold it once aligned.
+    pub fn alloc_layout(&mut self, layout: Layout) -> anyhow::Result<NonNull<u8>> {
+        let size = self.block_size(layout);
+        for i in 0..self.free.len() {
+            let block = self.free[i];
+            let start = unsafe { self.buf.add(block.offset) };
+            let padding = start.align_offset(layout.align());
+            if block.size < size.saturating_add(padding) {
+                continue;
+            }
+
+            let tail = FreeBlock {
+                offset: block.offset + padding + size,
+                size: block.size - padding - size,
+            };
+            let head = FreeBlock {
+                offset: block.offset,
+                size: padding,
+            };
+            self.free.remove(i);
+            let split = [head, tail].into_iter().filter(|b| b.size > 0);
+            self.free.splice(i..i, split);
+
+            let ptr = unsafe { start.add(padding) };
+            return Ok(NonNull::new(ptr).expect("buffer pointer is non-null"));
+        }
+        bail!("FreeListAllocator::alloc_layout => Out of memory: no free block fits")
+    }
+
+    /// Drops the value behind `ptr` and returns its bytes to the free list.
+    pub fn dealloc<T>(&mut self, ptr: RadPtr<T>) -> anyhow::Result<()> {
+        unsafe {
+            self.dealloc_layout(ptr.ptr.cast(), Layout::new::<T>())?;
+            std::ptr::drop_in_place(ptr.ptr);
+        }
+        Ok(())
+    }
+
+    /// Returns the range allocated for `layout` at `ptr` to the free list,
+    /// merging it with adjacent free blocks.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have come from `alloc_layout` on this allocator with the
+    /// same `layout`, and must not already have been freed.
+    pub unsafe fn dealloc_layout(&mut self, ptr: *mut u8, layout: Layout) -> anyhow::Result<()> {
+        self.free_range(ptr, self.block_size(layout))
+    }
+
+    /// Copies `src` into a fresh allocation; see `SliceHandle`.
+    pub fn alloc_slice<T: Copy>(&mut self, src: &[T]) -> anyhow::Result<SliceHandle<T>> {
+        let ptr = self
+            .alloc_layout(Layout::array::<T>(src.len())?)?
+            .cast::<T>();
+        unsafe { std::ptr::copy_nonoverlapping(src.as_ptr(), ptr.as_ptr(), src.len()) };
+        Ok(SliceHandle {
+            ptr,
+            len: src.len(),
+        })
+    }
+
+    /// Shrinks the slice behind `handle` to its first `new_len` elements and
+    /// returns the bytes past them to the free list, where they can serve new
+    /// allocations. Returns the shortened handle.
+    pub fn truncate_slice<T: Copy>(
+        &mut self,
+        handle: SliceHandle<T>,
+        new_len: usize,
+    ) -> anyhow::Result<SliceHandle<T>> {
+        if new_len > handle.len {
+            bail!("FreeListAllocator::truncate_slice => New length exceeds the slice's length");
+        }
+        let old_size = self.block_size(Layout::array::<T>(handle.len)?);
+        let new_size = self.block_size(Layout::array::<T>(new_len)?);
+        if new_size < old_size {
+            let tail = unsafe { handle.ptr.as_ptr().cast::<u8>().add(new_size) };
+            unsafe { self.free_range(tail, old_size - new_size)? };
+        }
+        Ok(SliceHandle {
+            ptr: handle.ptr,
+            len: new_len,
+        })
+    }
+
+    /// Returns the whole slice behind `handle` to the free list.
+    pub fn dealloc_slice<T: Copy>(&mut self, handle: SliceHandle<T>) -> anyhow::Result<()> {
+        let layout = Layout::array::<T>(handle.len)?;
+        unsafe { self.dealloc_layout(handle.ptr.as_ptr().cast(), layout) }
+    }
+
+    /// Puts `size` bytes at `ptr` back on the free list, merging them with
+    /// adjacent free blocks.
+    unsafe fn free_range(&mut self, ptr: *mut u8, size: usize) -> anyhow::Result<()> {
+        let Some(offset) = (ptr as usize).checked_sub(self.buf as usize) else {
+            bail!("FreeListAllocator::dealloc => Pointer does not belong to this allocator");
+        };
+        if offset + size > self.capacity() {
+            bail!("FreeListAllocator::dealloc => Pointer does not belong to this allocator");
+        }
+
+        let i = self.free.partition_point(|b| b.offset < offset);
+        let overlaps_prev = i > 0 && {
+            let prev = self.free[i - 1];
+            prev.offset + prev.size > offset
+        };
+        let overlaps_next = self.free.get(i).is_some_and(|b| b.offset < offset + size);
+        if overlaps_prev || overlaps_next {
+            bail!("FreeListAllocator::dealloc => Range is already free");
+        }
+
+        self.free.insert(i, FreeBlock { offset, size });
+        if self
+            .free
+            .get(i + 1)
+            .is_some_and(|b| b.offset == offset + size)
+        {
+            self.free[i].size += self.free.remove(i + 1).size;
+        }
+        if i > 0 && self.free[i - 1].offset + self.free[i - 1].size == offset {
+            self.free[i - 1].size += self.free.remove(i).size;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for FreeListAllocator {
+    fn drop(&mut self) {
+        unsafe { dealloc(self.buf, self.layout) }
+    }
+}
+
+/// A slice allocated by `FreeListAllocator::alloc_slice`, holding its own
+/// length so it can later be truncated or freed. Derefs to `[T]`.
+pub struct SliceHandle<T> {
+    ptr: NonNull<T>,
+    len: usize,
+}
+
+impl<T> SliceHandle<T> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl<T> Deref for SliceHandle<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> DerefMut for SliceHandle<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+pub type BumpPtr<T> = RadPtr<T>;
+
+/// Allocation failure that can be constructed without allocating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocError {
+    OutOfMemory,
+    /// A single request was larger than the allocator's configured limit.
+    ExceedsMaxAlloc,
+    /// The alignment padding a request needs would push the total past the
+    /// allocator's configured limit.
+    ExceedsMaxPadding,
+    /// The arena is locked read-only by `protect_readonly`.
+    ReadOnly,
+}
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfMemory => f.write_str("Cannot perform allocation: Allocator out of memory"),
+            Self::ExceedsMaxAlloc => f.write_str(
+                "Cannot perform allocation: Request exceeds the maximum allocation size",
+            ),
+            Self::ExceedsMaxPadding => f.write_str(
+                "Cannot perform allocation: Alignment padding would exceed the configured limit",
+            ),
+            Self::ReadOnly => f.write_str("Cannot perform allocation: Allocator is read-only"),
+        }
+    }
+}
+
+impl std::error::Error for AllocError {}
+
+/// What `BumpAllocator::clear` does to the bytes it frees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClearPolicy {
+    /// Only reset the cursor, leaving old contents in place.
+    #[default]
+    Fast,
+    /// Fill freed bytes with `POISON_BYTE`, so stale reads stand out.
+    Poison,
+    /// Wipe freed bytes with zeros in a way that can't be optimized away.
+    Zero,
+}
+
+/// Cost of a `BumpAllocator::clear`; see `BumpAllocator::clear_cost`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClearCost {
+    /// Nothing to drop, so clearing only resets the cursor (plus any
+    /// wipe required by the `ClearPolicy`).
+    Trivial,
+    /// This many destructors will run.
+    Drops(usize),
+}
+
+/// Byte written over freed memory under `ClearPolicy::Poison`.
+pub const POISON_BYTE: u8 = 0xDD;
+
+pub struct BumpAllocator {
+    buf: *mut u8,
+
+    layout: Layout,
+    capacity: usize,
+    size: usize,
+    drops: Vec<DropEntry>,
+    uniform_align: usize,
+    max_alloc: usize,
+    /// Alignment padding inserted since the arena was last emptied.
+    padding: usize,
+    max_padding: usize,
+    peak: usize,
+    epoch: u32,
+    clear_policy: ClearPolicy,
+    /// Allocations made since the arena was last cleared or rewound to 0.
+    alloc_count: usize,
+    /// Number of upcoming allocations to fail on purpose.
+    #[cfg(feature = "test-util")]
+    fail_next: usize,
+    /// Offset and layout of every live allocation, in allocation order.
+    #[cfg(feature = "debug-trace")]
+    trace: Vec<(usize, Layout)>,
+    /// `(offset, size, label)` of each allocation made with `alloc_tagged`.
+    #[cfg(feature = "debug-trace")]
+    labels: Vec<(usize, usize, &'static str)>,
+    /// Set to false inside `untracked_scope`, where `alloc_with_drop` skips
+    /// registering destructors.
+    tracking: bool,
+    /// Where `buf` came from, which decides how it is freed on drop.
+    storage: BumpStorage,
+    /// Process-unique id, for telling arenas apart in logs.
+    id: u64,
+    /// Length of the span currently mprotected read-only, or 0.
+    #[cfg(all(feature = "mmap", unix))]
+    protected: usize,
+    #[cfg(debug_assertions)]
+    needs_drop_hook: fn(&'static str),
+    /// `(offset, type id, type name)` of each typed allocation, when type
+    /// checking is on. Ids have lifetimes erased, so non-`'static` types are
+    /// tagged too; the name is only for messages.
+    #[cfg(all(debug_assertions, feature = "debug-trace"))]
+    type_tags: Option<Vec<(usize, TypeId, &'static str)>>,
+}
+
+/// Source of `BumpAllocator::id` values.
+static NEXT_ARENA_ID: AtomicU64 = AtomicU64::new(0);
+
+#[cfg(all(feature = "mmap", unix))]
+fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+/// Where a `BumpAllocator`'s buffer comes from, and so how it is freed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BumpStorage {
+    /// Allocated from the global allocator with `layout`.
+    Global,
+    /// Carved out of a parent arena, which owns the memory.
+    Parent,
+    /// A `reserved`-byte address space reservation, of which the first
+    /// `capacity` bytes are committed.
+    #[cfg(all(feature = "mmap", target_os = "linux"))]
+    Mapped { reserved: usize },
+    /// A mapping of `capacity` bytes followed by one `PROT_NONE` guard page.
+    #[cfg(all(feature = "mmap", unix))]
+    Guarded,
+}
+
+#[cfg(debug_assertions)]
+fn warn_needs_drop(type_name: &'static str) {
+    eprintln!(
+        "zen_alloc: warning: `{type_name}` needs drop but was allocated without drop tracking and will leak; use `alloc_with_drop`"
+    );
+}
+
+/// Destructor registered for a drop-tracked allocation of `size` bytes at
+/// `offset`.
+struct DropEntry {
+    offset: usize,
+    size: usize,
+    run: Box<dyn FnOnce()>,
+}
+
+impl BumpAllocator {
+    pub const DEFAULT_ALIGNMENT: usize = std::mem::align_of::<u8>();
+
+    pub fn new(size_bytes: usize) -> anyhow::Result<Self> {
+        Self::with_align(size_bytes, Self::DEFAULT_ALIGNMENT)
+    }
+
+    pub fn with_align(size_bytes: usize, align: usize) -> anyhow::Result<Self> {
+        unsafe {
+            let layout = Layout::from_size_align(size_bytes, align)?;
+            let buf = alloc_zeroed(layout);
+            if buf.is_null() {
+                bail!("BumpAllocator::with_align => Unable to allocate more memory from Global Allocator");
+            }
+            let s = Self::from_storage(buf, layout, size_bytes, BumpStorage::Global);
+            Ok(s)
+        }
+    }
+
+    /// An empty arena over `capacity` usable bytes at `buf`, freed on drop
+    /// according to `storage`.
+    fn from_storage(buf: *mut u8, layout: Layout, capacity: usize, storage: BumpStorage) -> Self {
+        Self {
+            buf,
+            layout,
+            capacity,
+
+            size: 0,
+            drops: Vec::new(),
+            uniform_align: 1,
+            max_alloc: usize::MAX,
+            padding: 0,
+            max_padding: usize::MAX,
+            peak: 0,
+            epoch: 0,
+            clear_policy: ClearPolicy::Fast,
+            alloc_count: 0,
+            #[cfg(feature = "test-util")]
+            fail_next: 0,
+            #[cfg(feature = "debug-trace")]
+            trace: Vec::new(),
+            #[cfg(feature = "debug-trace")]
+            labels: Vec::new(),
+            tracking: true,
+            storage,
+            id: NEXT_ARENA_ID.fetch_add(1, Ordering::Relaxed),
+            #[cfg(all(feature = "mmap", unix))]
+            protected: 0,
+            #[cfg(debug_assertions)]
+            needs_drop_hook: warn_needs_drop,
+            #[cfg(all(debug_assertions, feature = "debug-trace"))]
+            type_tags: None,
+        }
+    }
+
+    /// Creates an arena where every allocation starts at a multiple of `align`
+    /// and is padded to a multiple of it, whatever the type's own alignment.
+    pub fn with_uniform_align(size_bytes: usize, align: usize) -> anyhow::Result<Self> {
+        let mut s = Self::with_align(size_bytes, align)?;
+        s.uniform_align = align;
+        Ok(s)
+    }
+
+    /// Creates an arena that reserves `max` bytes of address space up front
+    /// and commits `initial` of them, committing more as allocations need it
+    /// up to `max`. The base never moves, so pointers stay valid across
+    /// growth, unlike a `realloc`-based arena.
+    ///
+    /// Growth commits further pages of the reservation with `mprotect`
+    /// instead of calling `mremap`. Without `MREMAP_MAYMOVE`, `mremap` can
+    /// only grow a mapping in place while the pages after it are unmapped,
+    /// and nothing stops another mapping from landing there. Reserving `max`
+    /// up front keeps those pages for this arena.
+    #[cfg(all(feature = "mmap", target_os = "linux"))]
+    pub fn new_mremap(initial: usize, max: usize) -> anyhow::Result<Self> {
+        let page = page_size();
+        let reserved = max.next_multiple_of(page);
+        let initial = initial.next_multiple_of(page);
+        if reserved == 0 || initial > reserved {
+            bail!("BumpAllocator::new_mremap => Initial size must not exceed max, and max must be non-zero");
+        }
+        let layout = Layout::from_size_align(reserved, page)?;
+        unsafe {
+            let base = libc::mmap(
+                std::ptr::null_mut(),
+                reserved,
+                libc::PROT_NONE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_NORESERVE,
+                -1,
+                0,
+            );
+            if base == libc::MAP_FAILED {
+                bail!("BumpAllocator::new_mremap => Unable to reserve address space");
+            }
+            if libc::mprotect(base, initial, libc::PROT_READ | libc::PROT_WRITE) != 0 {
+                libc::munmap(base, reserved);
+                bail!("BumpAllocator::new_mremap => Unable to commit initial pages");
+            }
+
+            let storage = BumpStorage::Mapped { reserved };
+            Ok(Self::from_storage(base.cast(), layout, initial, storage))
+        }
+    }
+
+    /// Creates an arena of `size` bytes rounded up to whole pages, mapped
+    /// directly before a `PROT_NONE` guard page, so a write past capacity
+    /// faults at once instead of corrupting whatever follows.
+    #[cfg(all(feature = "mmap", unix))]
+    pub fn new_guarded(size: usize) -> anyhow::Result<Self> {
+        let page = page_size();
+        let capacity = size.next_multiple_of(page);
+        if capacity == 0 {
+            bail!("BumpAllocator::new_guarded => Size must be non-zero");
+        }
+        let layout = Layout::from_size_align(capacity, page)?;
+        unsafe {
+            let base = libc::mmap(
+                std::ptr::null_mut(),
+                capacity + page,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            );
+            if base == libc::MAP_FAILED {
+                bail!("BumpAllocator::new_guarded => Unable to map memory");
+            }
+            if libc::mprotect(
+                base.cast::<u8>().add(capacity).cast(),
+                page,
+                libc::PROT_NONE,
+            ) != 0
+            {
+                libc::munmap(base, capacity + page);
+                bail!("BumpAllocator::new_guarded => Unable to protect the guard page");
+            }
+
+            let storage = BumpStorage::Guarded;
+            Ok(Self::from_storage(base.cast(), layout, capacity, storage))
+        }
+    }
+
+    /// Creates an arena whose base is aligned for the most-aligned of the
+    /// `layouts` it is expected to serve, so the first allocation of any of
+    /// them needs no padding.
+    pub fn optimized_for(size_bytes: usize, layouts: &[Layout]) -> anyhow::Result<Self> {
+        let align = layouts
+            .iter()
+            .map(Layout::align)
+            .fold(Self::DEFAULT_ALIGNMENT, usize::max);
+        Self::with_align(size_bytes, align)
+    }
+
+    /// Creates an arena whose `clear` treats freed bytes according to `policy`.
+    pub fn with_clear_policy(size_bytes: usize, policy: ClearPolicy) -> anyhow::Result<Self> {
+        let mut s = Self::new(size_bytes)?;
+        s.clear_policy = policy;
+        Ok(s)
+    }
+
+    /// Deep-copies the used region into a new buffer of the same capacity and
+    /// alignment. Only meaningful for POD contents: values are copied bytewise
+    /// and pointers into `self` still point into `self`.
+    pub fn try_clone(&self) -> anyhow::Result<Self> {
+        let mut other = Self::with_align(self.capacity, self.layout.align())?;
+        other.uniform_align = self.uniform_align;
+        other.max_alloc = self.max_alloc;
+        other.max_padding = self.max_padding;
+        other.padding = self.padding;
+        other.clear_policy = self.clear_policy;
+        other.alloc_count = self.alloc_count;
+        other.peak = self.peak;
+        other.epoch = self.epoch;
+        #[cfg(debug_assertions)]
+        {
+            other.needs_drop_hook = self.needs_drop_hook;
+        }
+        unsafe { std::ptr::copy_nonoverlapping(self.buf, other.buf, self.size) };
+        other.size = self.size;
+        Ok(other)
+    }
+
+    /// Id assigned at construction, unique among all arenas created by this
+    /// process and fixed for the arena's lifetime. Clones get a fresh id.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Bytes currently allocated, including alignment padding.
+    pub fn used(&self) -> usize {
+        self.size
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.capacity - self.size
+    }
+
+    /// Allocations made since the arena was last cleared. Rewinding to a
+    /// non-zero marker does not lower the count.
+    pub fn allocation_count(&self) -> usize {
+        self.alloc_count
+    }
+
+    /// `used() / allocation_count()`, padding included; 0 for an empty arena.
+    pub fn average_alloc_size(&self) -> f64 {
+        if self.alloc_count == 0 {
+            return 0.0;
+        }
+        self.size as f64 / self.alloc_count as f64
+    }
+
+    pub fn backing(&self) -> Backing {
+        match self.storage {
+            BumpStorage::Global => Backing::Heap,
+            BumpStorage::Parent => Backing::BorrowedSlice,
+            #[cfg(all(feature = "mmap", target_os = "linux"))]
+            BumpStorage::Mapped { .. } => Backing::Mmap,
+            #[cfg(all(feature = "mmap", unix))]
+            BumpStorage::Guarded => Backing::Mmap,
+        }
+    }
+
+    /// Highest `used()` value seen since the arena was created.
+    pub fn peak(&self) -> usize {
+        self.peak
+    }
+
+    /// Bytes of the region allocated so far, which may include padding
+    /// inside stored values and never-written uninitialized allocations.
+    pub fn as_uninit_bytes(&self) -> &[MaybeUninit<u8>] {
+        unsafe { std::slice::from_raw_parts(self.buf.cast(), self.size) }
+    }
+
+    /// Bytes of the region allocated so far. The buffer starts out zeroed,
+    /// so alignment gaps are always initialized.
+    ///
+    /// # Safety
+    /// Every value ever stored in the arena must have been free of padding
+    /// (e.g. `bytemuck::Pod`), and every uninitialized allocation in the used
+    /// region must have been fully written.
+    pub unsafe fn as_bytes(&self) -> &[u8] {
+        std::slice::from_raw_parts(self.buf, self.size)
+    }
+
+    /// Owned copy of the used bytes, independent of the arena.
+    ///
+    /// # Safety
+    /// Same as `as_bytes`.
+    pub unsafe fn to_boxed_slice(&self) -> Box<[u8]> {
+        self.as_bytes().into()
+    }
+
+    /// Stable 64-bit FNV-1a hash of `as_bytes()`, the same across runs and
+    /// platforms. Pointers are hashed as-is.
+    ///
+    /// # Safety
+    /// Same as `as_bytes`.
+    pub unsafe fn content_hash(&self) -> u64 {
+        fnv1a(self.as_bytes())
+    }
+
+    /// `xxd`-style dump of `as_bytes()`: 16 bytes per line as an offset, hex
+    /// in 2-byte groups, and printable ASCII with `.` for anything else.
+    ///
+    /// # Safety
+    /// Same as `as_bytes`.
+    pub unsafe fn hexdump(&self) -> String {
+        use std::fmt::Write;
+
+        let mut out = String::new();
+        for (line, chunk) in self.as_bytes().chunks(16).enumerate() {
+            let _ = write!(out, "{:08x}:", line * 16);
+            for i in 0..16 {
+                if i % 2 == 0 {
+                    out.push(' ');
+                }
+                match chunk.get(i) {
+                    Some(b) => {
+                        let _ = write!(out, "{b:02x}");
+                    }
+                    None => out.push_str("  "),
+                }
+            }
+            out.push_str("  ");
+            out.extend(chunk.iter().map(|&b| match b {
+                0x20..=0x7e => b as char,
+                _ => '.',
+            }));
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Byte offset of `ptr` within the allocated region, or `None` if the
+    /// pointer doesn't point into this arena.
+    pub fn offset_of<T>(&self, ptr: &BumpPtr<T>) -> Option<usize> {
+        let offset = (ptr.ptr as usize).checked_sub(self.buf as usize)?;
+        let end = offset.checked_add(std::mem::size_of::<T>())?;
+        (end <= self.size).then_some(offset)
+    }
+
+    /// Resolves a byte offset previously obtained from `offset_of` back into a
+    /// pointer. Fails if the value would extend past the allocated region or
+    /// the address is misaligned for `T`.
+    ///
+    /// # Safety
+    /// The bytes at `offset` must hold an initialized `T`, e.g. one allocated
+    /// there as a `T`.
+    pub unsafe fn ptr_from_offset<T>(&self, offset: usize) -> Option<BumpPtr<T>> {
+        let end = offset.checked_add(std::mem::size_of::<T>())?;
+        if end > self.size {
+            return None;
+        }
+        let ptr = self.buf.add(offset);
+        (ptr.align_offset(align_of::<T>()) == 0).then(|| RadPtr { ptr: ptr.cast() })
+    }
+
+    /// Starts recording the type of every typed allocation so `read_checked`
+    /// can catch reads through the wrong type. Only has an effect in debug
+    /// builds with the `debug-trace` feature; otherwise nothing is recorded.
+    pub fn track_types(&mut self) {
+        #[cfg(all(debug_assertions, feature = "debug-trace"))]
+        self.type_tags.get_or_insert_with(Vec::new);
+    }
+
+    /// Reads the `T` at `offset`. In debug builds with `debug-trace` and
+    /// `track_types` on, panics if the value stored there was allocated as a
+    /// different type.
+    /// Panics if `offset` is out of bounds or misaligned for `T`.
+    ///
+    /// # Safety
+    /// `offset` must hold an initialized `T`. The type check is a debugging
+    /// aid that only debug builds with `debug-trace` perform, so it cannot
+    /// be relied on.
+    pub unsafe fn read_checked<T: 'static>(&self, offset: usize) -> &T {
+        #[cfg(all(debug_assertions, feature = "debug-trace"))]
+        if let Some(tags) = &self.type_tags {
+            if let Ok(i) = tags.binary_search_by_key(&offset, |&(o, ..)| o) {
+                let (_, recorded, recorded_name) = tags[i];
+                assert!(
+                    recorded == TypeId::of::<T>(),
+                    "BumpAllocator::read_checked => Type mismatch at offset {offset}: allocated as `{recorded_name}`, read as `{}`",
+                    std::any::type_name::<T>()
+                );
+            }
+        }
+        let ptr = self
+            .ptr_from_offset::<T>(offset)
+            .expect("BumpAllocator::read_checked => Offset does not hold a valid value");
+        unsafe { &*ptr.ptr }
+    }
+
+    #[inline]
+    fn tag_type<T>(&mut self, _ptr: *mut u8) {
+        #[cfg(all(debug_assertions, feature = "debug-trace"))]
+        if let Some(tags) = &mut self.type_tags {
+            let offset = _ptr as usize - self.buf as usize;
+            tags.push((offset, typeid::of::<T>(), std::any::type_name::<T>()));
+        }
+    }
+
+    /// Whether `a` and `b` are the two most recent allocations and `b` starts
+    /// exactly where `a` ends, with no padding in between.
+    pub fn last_two_contiguous<A, B>(&self, a: &BumpPtr<A>, b: &BumpPtr<B>) -> bool {
+        let (Some(a_off), Some(b_off)) = (self.offset_of(a), self.offset_of(b)) else {
+            return false;
+        };
+        a_off + std::mem::size_of::<A>() == b_off && b_off + std::mem::size_of::<B>() == self.size
+    }
+
+    /// If `a` and `b` are the two most recent, adjacent allocations, returns
+    /// the bytes of both as one contiguous span.
+    ///
+    /// # Safety
+    /// `A` and `B` must be free of padding, and any bytes written through the
+    /// span must be valid for them (e.g. both `bytemuck::Pod`). `a` and `b`
+    /// must not be used while the span is alive.
+    pub unsafe fn coalesce_last_two<A, B>(
+        &mut self,
+        a: &BumpPtr<A>,
+        b: &BumpPtr<B>,
+    ) -> Option<&mut [u8]> {
+        if !self.last_two_contiguous(a, b) {
+            return None;
+        }
+        let start = self.offset_of(a)?;
+        Some(unsafe { std::slice::from_raw_parts_mut(self.buf.add(start), self.size - start) })
+    }
+
+    /// Number of times the arena has been cleared.
+    pub fn current_epoch(&self) -> u32 {
+        self.epoch
+    }
+
+    /// Allocates `data` and returns the epoch it was allocated in. If
+    /// `current_epoch()` no longer matches, the arena has been cleared and
+    /// the pointer is stale.
+    pub fn alloc_gen<T>(&mut self, data: T) -> anyhow::Result<(BumpPtr<T>, u32)> {
+        let ptr = self.alloc(data)?;
+        Ok((ptr, self.epoch))
+    }
+
+    /// Converts `ptr` into an offset handle stamped with the current epoch,
+    /// or `None` if it doesn't point into this arena.
+    pub fn stamp<T>(&self, ptr: &BumpPtr<T>) -> Option<EpochOffset> {
+        Some(EpochOffset {
+            offset: self.offset_of(ptr)?,
+            epoch: self.epoch,
+        })
+    }
+
+    /// Resolves a handle from `stamp`. Fails if the arena has been cleared
+    /// since the handle was stamped, rather than resolving to whatever was
+    /// written at that offset afterwards.
+    ///
+    /// # Safety
+    /// `T` must be the type the handle's value was stamped as, as for
+    /// `ptr_from_offset`.
+    pub unsafe fn resolve<T>(&self, handle: EpochOffset) -> anyhow::Result<BumpPtr<T>> {
+        if handle.epoch != self.epoch {
+            bail!("BumpAllocator::resolve => Handle is from a previous epoch");
+        }
+        match self.ptr_from_offset(handle.offset) {
+            Some(ptr) => Ok(ptr),
+            None => bail!("BumpAllocator::resolve => Offset does not hold a valid value"),
+        }
+    }
+
+    /// Allocates `data` and returns a typed offset handle to it instead of a
+    /// pointer. The handle survives `snapshot`/`restore`, including into
+    /// another arena, and stops resolving once the arena is cleared.
+    pub fn alloc_offset<T: Copy>(&mut self, data: T) -> anyhow::Result<OffsetHandle<T>> {
+        let ptr = self.alloc(data)?;
+        Ok(OffsetHandle {
+            raw: self
+                .stamp(&ptr)
+                .expect("BumpAllocator::alloc_offset => Fresh allocation is in the arena"),
+            _marker: PhantomData,
+        })
+    }
+
+    /// Byte-for-byte copy of the used region, along with the epoch, so that
+    /// `OffsetHandle`s taken before the snapshot resolve after `restore`.
+    /// Drop-tracked values are copied as plain bytes; their destructors stay
+    /// with this arena.
+    ///
+    /// # Safety
+    /// Same as `as_bytes`.
+    pub unsafe fn snapshot(&self) -> BumpSnapshot {
+        BumpSnapshot {
+            bytes: self.as_bytes().to_vec(),
+            align: self.layout.align(),
+            epoch: self.epoch,
+        }
+    }
+
+    /// Frees everything, then replaces the arena's contents and epoch with
+    /// `snapshot`'s. Fails, leaving the arena untouched, if the snapshot
+    /// doesn't fit or came from a more strictly aligned arena.
+    pub fn restore(&mut self, snapshot: &BumpSnapshot) -> anyhow::Result<()> {
+        let len = snapshot.bytes.len();
+        if len > self.capacity && !self.commit_more(len - self.size) {
+            bail!("BumpAllocator::restore => Snapshot does not fit in this arena");
+        }
+        if self.layout.align() < snapshot.align {
+            bail!("BumpAllocator::restore => Arena is less aligned than the snapshot's source");
+        }
+        self.make_writable();
+        self.rewind(0);
+        unsafe { std::ptr::copy_nonoverlapping(snapshot.bytes.as_ptr(), self.buf, len) };
+        self.size = len;
+        self.peak = self.peak.max(len);
+        self.epoch = snapshot.epoch;
+        Ok(())
+    }
+
+    /// Calls `f` with the index and bytes of each field in `schema`. Fields
+    /// that fall outside the allocated region are skipped.
+    ///
+    /// # Safety
+    /// Same as `as_bytes`.
+    pub unsafe fn visit(&self, schema: &Schema, mut f: impl FnMut(usize, &[u8])) {
+        let bytes = self.as_bytes();
+        for (i, field) in schema.fields.iter().enumerate() {
+            if let Some(field_bytes) = field
+                .offset
+                .checked_add(field.size)
+                .and_then(|end| bytes.get(field.offset..end))
+            {
+                f(i, field_bytes);
+            }
+        }
+    }
+
+    #[inline]
+    pub fn alloc<T>(&mut self, data: T) -> anyhow::Result<BumpPtr<T>> {
+        Ok(self.try_alloc(data)?)
+    }
+
+    /// Same as `alloc`, but reports failure with the lightweight `AllocError`
+    /// so neither path touches the heap; `alloc` only builds an
+    /// `anyhow::Error` once this has already failed.
+    ///
+    /// In debug builds, allocating a type that needs dropping through this
+    /// untracked path reports it via the needs-drop hook, since the value
+    /// will be leaked; use `alloc_with_drop` for such types.
+    #[inline]
+    pub fn try_alloc<T>(&mut self, data: T) -> Result<BumpPtr<T>, AllocError> {
+        #[cfg(debug_assertions)]
+        if std::mem::needs_drop::<T>() {
+            (self.needs_drop_hook)(std::any::type_name::<T>());
+        }
+        self.write(data)
+    }
+
+    /// Moves `data` into freshly bumped memory, without drop tracking.
+    #[inline]
+    fn write<T>(&mut self, data: T) -> Result<BumpPtr<T>, AllocError> {
+        self.write_aligned(data, align_of::<T>())
+    }
+
+    /// Like `write`, at an address aligned to `align`, which must be a power
+    /// of two no smaller than `align_of::<T>()`.
+    #[inline]
+    fn write_aligned<T>(&mut self, data: T, align: usize) -> Result<BumpPtr<T>, AllocError> {
+        unsafe {
+            let ptr = self.bump(std::mem::size_of::<T>(), align)?.cast::<T>();
+            std::ptr::write(ptr, data);
+            self.tag_type::<T>(ptr.cast());
+
+            let sp = RadPtr { ptr };
+            Ok(sp)
+        }
+    }
+
+    /// Allocates `data` at an address with the same alignment `like`'s
+    /// address has, up to `CACHE_LINE_SIZE`, e.g. to put a counter on a lock's
+    /// cache line. Never less aligned than `T` requires.
+    pub fn alloc_aligned_like<T, U>(
+        &mut self,
+        data: T,
+        like: &RadPtr<U>,
+    ) -> anyhow::Result<BumpPtr<T>> {
+        let addr = like.ptr as usize;
+        let natural = if addr == 0 {
+            CACHE_LINE_SIZE
+        } else {
+            addr & addr.wrapping_neg()
+        };
+        let align = natural.min(CACHE_LINE_SIZE).max(align_of::<T>());
+        Ok(self.write_aligned(data, align)?)
+    }
+
+    /// Reserves uninitialized memory described by `layout`.
+    pub fn alloc_layout(&mut self, layout: Layout) -> anyhow::Result<NonNull<u8>> {
+        let ptr = self.bump(layout.size(), layout.align())?;
+        Ok(unsafe { NonNull::new_unchecked(ptr) })
+    }
+
+    /// Reserves `len` uninitialized bytes to be filled in over several passes,
+    /// e.g. as chunks of a stream arrive. Once every byte in a range has been
+    /// written, `assume_init_bytes` turns it into a plain `&[u8]`. Reading a
+    /// byte before writing it is undefined behaviour.
+    pub fn alloc_uninit_bytes(&mut self, len: usize) -> anyhow::Result<&mut [MaybeUninit<u8>]> {
+        let ptr = self.bump(len, 1)?.cast::<MaybeUninit<u8>>();
+        Ok(unsafe { std::slice::from_raw_parts_mut(ptr, len) })
+    }
+
+    /// Reserves aligned, uninitialized space for `len` values of `T`, the
+    /// in-place counterpart to `alloc_slice` for when there is no source to
+    /// copy from. Once every slot is written the slice can be reinterpreted as
+    /// `&mut [T]`. `len == 0` yields an empty slice without touching the arena.
+    pub fn alloc_uninit_slice<T>(&mut self, len: usize) -> anyhow::Result<&mut [MaybeUninit<T>]> {
+        if len == 0 {
+            return Ok(&mut []);
+        }
+        let layout = Layout::array::<T>(len)?;
+        let ptr = self
+            .bump(layout.size(), layout.align())?
+            .cast::<MaybeUninit<T>>();
+        Ok(unsafe { std::slice::from_raw_parts_mut(ptr, len) })
+    }
+
+    /// Allocates a `rows` x `cols` grid filled with `init`, stored row-major
+    /// in one aligned block; see `MatrixView`.
+    pub fn alloc_matrix<T: Copy>(
+        &mut self,
+        rows: usize,
+        cols: usize,
+        init: T,
+    ) -> anyhow::Result<MatrixView<'_, T>> {
+        let Some(len) = rows.checked_mul(cols) else {
+            bail!("BumpAllocator::alloc_matrix => Matrix size overflows usize");
+        };
+        let slots = self.alloc_uninit_slice::<T>(len)?;
+        for slot in slots.iter_mut() {
+            slot.write(init);
+        }
+        let data = unsafe { &mut *(slots as *mut [MaybeUninit<T>] as *mut [T]) };
+        Ok(MatrixView { data, rows, cols })
+    }
+
+    /// Reserves `bytes` from this arena as a child arena; see `SubArena`. The
+    /// child's base is wherever the cursor lands.
+    pub fn sub(&mut self, bytes: usize) -> anyhow::Result<SubArena<'_>> {
+        self.sub_aligned(bytes, 1)
+    }
+
+    /// Like `sub`, but the child's base is aligned to `align`, so its first
+    /// allocation meets that alignment whatever the parent's cursor was.
+    pub fn sub_aligned(&mut self, bytes: usize, align: usize) -> anyhow::Result<SubArena<'_>> {
+        let layout = Layout::from_size_align(bytes, align)?;
+        let buf = self.alloc_layout(layout)?.as_ptr();
+        unsafe { std::ptr::write_bytes(buf, 0, bytes) };
+        let mut bump = Self::from_storage(buf, layout, bytes, BumpStorage::Parent);
+        bump.clear_policy = self.clear_policy;
+        #[cfg(debug_assertions)]
+        {
+            bump.needs_drop_hook = self.needs_drop_hook;
+        }
+        Ok(SubArena {
+            bump,
+            _parent: PhantomData,
+        })
+    }
+
+    /// Shrinks the top allocation, `old_len` bytes starting at `ptr`, to
+    /// `new_len` bytes, giving the tail back to the arena. Fails if that
+    /// allocation is not the most recent one or `new_len` exceeds `old_len`.
+    pub fn shrink_last(
+        &mut self,
+        ptr: NonNull<u8>,
+        old_len: usize,
+        new_len: usize,
+    ) -> anyhow::Result<()> {
+        let Some(offset) = (ptr.as_ptr() as usize).checked_sub(self.buf as usize) else {
+            bail!("BumpAllocator::shrink_last => Pointer does not belong to this arena");
+        };
+        if offset.checked_add(old_len) != Some(self.size) || new_len > old_len {
+            bail!("BumpAllocator::shrink_last => Only the top allocation can be shrunk");
+        }
+        if self
+            .drops
+            .last()
+            .is_some_and(|e| e.offset + e.size > offset)
+        {
+            bail!("BumpAllocator::shrink_last => Drop-tracked allocations cannot be shrunk");
+        }
+        self.truncate_top(offset, new_len);
+        Ok(())
+    }
+
+    /// Cuts the top allocation, which starts at `offset`, down to `new_len`
+    /// bytes, trimming the tracking that covered the removed tail. The caller
+    /// has checked that it is the top allocation and not drop-tracked.
+    fn truncate_top(&mut self, offset: usize, new_len: usize) {
+        let end = offset + new_len;
+        // A shrunk value no longer has its type's size, so its tag goes too.
+        #[cfg(all(debug_assertions, feature = "debug-trace"))]
+        if let Some(tags) = self.type_tags.as_mut().filter(|_| end < self.size) {
+            tags.retain(|&(o, ..)| o < offset);
+        }
+        #[cfg(feature = "debug-trace")]
+        {
+            self.trace.retain(|&(o, _)| o < end);
+            if let Some((_, layout)) = self.trace.last_mut().filter(|(o, _)| *o == offset) {
+                *layout = Layout::from_size_align(new_len, layout.align())
+                    .expect("shrinking keeps the layout valid");
+            }
+            self.labels.retain(|&(o, _, _)| o < end);
+            if let Some((_, size, _)) = self.labels.last_mut().filter(|(o, ..)| *o == offset) {
+                *size = new_len;
+            }
+        }
+        self.size = end;
+    }
+
+    /// Reserves up to `max` bytes to be written incrementally; on `finish`,
+    /// the unwritten tail is returned to the arena. See `RegionBuilder`.
+    pub fn reserve_region(&mut self, max: usize) -> anyhow::Result<RegionBuilder<'_>> {
+        let start = self.bump(max, 1)?;
+        Ok(RegionBuilder {
+            arena: self,
+            start,
+            len: 0,
+            max,
+        })
+    }
+
+    /// Reserves `capacity` bytes and returns a cursor that writes into them
+    /// sequentially; see `ArenaWriter`.
+    pub fn writer(&mut self, capacity: usize) -> anyhow::Result<ArenaWriter<'_>> {
+        let buf = self.bump(capacity, 1)?;
+        Ok(ArenaWriter {
+            buf,
+            len: 0,
+            capacity,
+            _arena: PhantomData,
+        })
+    }
+
+    /// Reserves uninitialized room for a `T` to be written in place, field by
+    /// field, through the returned builder; see `UninitBuilder`.
+    pub fn alloc_builder<T>(&mut self) -> anyhow::Result<UninitBuilder<'_, T>> {
+        let ptr = self.alloc_layout(Layout::new::<T>())?.cast::<T>();
+        Ok(UninitBuilder {
+            ptr,
+            _arena: PhantomData,
+        })
+    }
+
+    /// Reserves room for `capacity` values of `T` and returns an empty vector
+    /// over it that can be pushed to until full.
+    pub fn alloc_vec<T>(&mut self, capacity: usize) -> anyhow::Result<ArenaVec<'_, T>> {
+        let ptr = self
+            .alloc_layout(Layout::array::<T>(capacity)?)?
+            .cast::<T>();
+        Ok(ArenaVec {
+            ptr,
+            len: 0,
+            capacity,
+            _arena: PhantomData,
+        })
+    }
+
+    /// Reserves memory for `layout` and lets `init` populate it in place,
+    /// for types the typed API can't express (e.g. a header followed by a
+    /// runtime-sized tail).
+    ///
+    /// `init` receives a pointer to `layout.size()` writable bytes aligned to
+    /// `layout.align()`. It must not write outside that range, and must leave
+    /// the memory initialized however the caller later intends to read it.
+    /// The bytes are never dropped by the arena.
+    pub fn alloc_custom(
+        &mut self,
+        layout: Layout,
+        init: impl FnOnce(*mut u8),
+    ) -> anyhow::Result<NonNull<u8>> {
+        let ptr = self.alloc_layout(layout)?;
+        init(ptr.as_ptr());
+        Ok(ptr)
+    }
+
+    /// Copies `src` into the arena and returns the copy, with its length
+    /// bundled in; see `ArenaSlice`.
+    pub fn alloc_slice<T: Copy>(&mut self, src: &[T]) -> anyhow::Result<ArenaSlice<'_, T>> {
+        let ptr = self.alloc_slice_ptr(src)?;
+        Ok(ArenaSlice {
+            ptr: ptr.cast(),
+            len: src.len(),
+            _arena: PhantomData,
+        })
+    }
+
+    /// Copies `src` into the arena and returns a fat raw pointer to the copy,
+    /// suitable for handing across FFI. The pointer stays valid until the
+    /// arena is cleared, rewound past it, or dropped; the arena must also not
+    /// be moved-from in a way that frees its buffer in the meantime.
+    pub fn alloc_slice_ptr<T: Copy>(&mut self, src: &[T]) -> anyhow::Result<NonNull<[T]>> {
+        let ptr = self
+            .bump(std::mem::size_of_val(src), align_of::<T>())?
+            .cast::<T>();
+        unsafe {
+            std::ptr::copy_nonoverlapping(src.as_ptr(), ptr, src.len());
+            let slice = std::ptr::slice_from_raw_parts_mut(ptr, src.len());
+            Ok(NonNull::new_unchecked(slice))
+        }
+    }
+
+    /// Allocates `data` and returns a reference borrowing the arena. `T` may
+    /// itself borrow, e.g. hold `&str` slices of an input being parsed
+    /// zero-copy, as long as that input outlives the returned reference.
+    pub fn alloc_borrowing<'a, T: 'a>(&'a mut self, data: T) -> anyhow::Result<&'a mut T> {
+        let ptr = self.alloc(data)?;
+        Ok(unsafe { &mut *ptr.ptr })
+    }
+
+    /// Allocates `data` and returns a reference that compares by address; see
+    /// `StableRef`.
+    pub fn alloc_stable<T>(&mut self, data: T) -> anyhow::Result<StableRef<'_, T>> {
+        let ptr = self.alloc(data)?;
+        Ok(StableRef(unsafe { &*ptr.ptr }))
+    }
+
+    /// Allocates `data` behind a `Copy` handle that only derefs to `&T`.
+    pub fn alloc_shared<T>(&mut self, data: T) -> anyhow::Result<ArenaShared<'_, T>> {
+        let ptr = self.alloc(data)?;
+        Ok(ArenaShared(unsafe { &*ptr.ptr }))
+    }
+
+    /// Allocates `data` and registers its destructor to run when the arena is
+    /// cleared, rewound past it, or dropped.
+    ///
+    /// Destructors are guaranteed to run in reverse allocation order, so a
+    /// value's destructor may still use anything allocated before it.
+    pub fn alloc_with_drop<T: 'static>(&mut self, data: T) -> anyhow::Result<BumpPtr<T>> {
+        let sp = self.write(data)?;
+        if !self.tracking {
+            return Ok(sp);
+        }
+        let ptr = sp.ptr;
+        self.track(
+            ptr,
+            Box::new(move || unsafe { std::ptr::drop_in_place(ptr) }),
+        );
+        Ok(sp)
+    }
+
+    /// Like `alloc_with_drop`, but also returns a closure that runs the
+    /// destructor early. The value is then marked dropped so the arena won't
+    /// drop it a second time.
+    pub fn alloc_with_dropper<T: 'static>(
+        &mut self,
+        data: T,
+    ) -> anyhow::Result<(BumpPtr<T>, impl FnOnce())> {
+        let sp = self.write(data)?;
+        let ptr = sp.ptr;
+        let alive = Rc::new(Cell::new(true));
+        let dropper = {
+            let alive = alive.clone();
+            move || {
+                if alive.replace(false) {
+                    unsafe { std::ptr::drop_in_place(ptr) }
+                }
+            }
+        };
+        self.track(
+            ptr,
+            Box::new(move || {
+                if alive.replace(false) {
+                    unsafe { std::ptr::drop_in_place(ptr) }
+                }
+            }),
+        );
+        Ok((sp, dropper))
+    }
+
+    /// Allocates `data` and registers `finalize` to run on it, in the same
+    /// reverse order as destructors, when the arena is cleared, rewound past
+    /// it, or dropped. Unlike `alloc_with_drop` this works for types without a
+    /// `Drop` impl, e.g. a `Copy` OS handle that still needs closing.
+    pub fn alloc_with_finalizer<T: 'static>(
+        &mut self,
+        data: T,
+        finalize: impl FnOnce(&mut T) + 'static,
+    ) -> anyhow::Result<BumpPtr<T>> {
+        let sp = self.write(data)?;
+        let ptr = sp.ptr;
+        self.track(ptr, Box::new(move || finalize(unsafe { &mut *ptr })));
+        Ok(sp)
+    }
+
+    /// Allocates `data`, typically a handle to a C-allocated resource, and
+    /// registers `free_fn` to be called with a pointer to it when the arena is
+    /// cleared, rewound past it, or dropped.
+    pub fn alloc_ffi<T: 'static>(
+        &mut self,
+        data: T,
+        free_fn: unsafe extern "C" fn(*mut T),
+    ) -> anyhow::Result<BumpPtr<T>> {
+        let sp = self.write(data)?;
+        let ptr = sp.ptr;
+        self.track(ptr, Box::new(move || unsafe { free_fn(ptr) }));
+        Ok(sp)
+    }
+
+    /// Clones the value behind `ptr` out of the arena, leaving the original in
+    /// place.
+    pub fn copy_out<T: Clone>(&self, ptr: &BumpPtr<T>) -> T {
+        (**ptr).clone()
+    }
+
+    /// Moves the value behind `ptr` out of the arena. Its registered
+    /// destructor, if any, is dropped so clear or drop won't run it again, and
+    /// if it was the top allocation the cursor moves back over it.
+    ///
+    /// # Safety
+    /// `ptr` must point to a live `T` allocated from this arena, and neither it
+    /// nor any copy of it may be used afterwards.
+    pub unsafe fn move_out<T>(&mut self, ptr: BumpPtr<T>) -> T {
+        let value = std::ptr::read(ptr.ptr);
+        let Some(offset) = self.offset_of(&ptr) else {
+            return value;
+        };
+        if let Some(i) = self.drops.iter().rposition(|e| e.offset == offset) {
+            self.drops.remove(i);
+        }
+        let top = offset + std::mem::size_of::<T>() == self.size;
+        if top && self.drops.last().is_none_or(|e| e.offset < offset) {
+            self.reset_cursor(offset);
+        }
+        value
+    }
+
+    /// Allocates items from `it` until the next one would not fit, and returns
+    /// how many were stored. An item is only pulled from `it` once there is
+    /// room for it, so the iterator is left at the first unstored item.
+    pub fn fill_from<T>(&mut self, it: &mut impl Iterator<Item = T>) -> usize {
+        let mut stored = 0;
+        while self.can_alloc::<T>() {
+            let Some(item) = it.next() else {
+                break;
+            };
+            self.try_alloc(item)
+                .expect("BumpAllocator::fill_from => Checked for room before allocating");
+            stored += 1;
+        }
+        stored
+    }
+
+    /// Collects an iterator of unknown length into a contiguous slice at the
+    /// top of the arena, growing it one element at a time. If the arena runs
+    /// out of room the collected elements are dropped and the space reclaimed.
+    pub fn collect_slice<T>(&mut self, it: impl Iterator<Item = T>) -> anyhow::Result<&mut [T]> {
+        let slice = self.collect_raw(it)?;
+        Ok(unsafe { &mut *slice.as_ptr() })
+    }
+
+    fn collect_raw<T>(&mut self, it: impl Iterator<Item = T>) -> anyhow::Result<NonNull<[T]>> {
+        let marker = self.marker();
+        let (_, align) = self.uniform(0, align_of::<T>());
+        let start = self.reserve(0, align)?.cast::<T>();
+        let mut len = 0;
+        for item in it {
+            // The cursor stays aligned for `T` after each element, so every
+            // reservation lands directly after the previous one. Uniform
+            // alignment applies to the slice as a whole, not to each element.
+            match self.reserve(std::mem::size_of::<T>(), align_of::<T>()) {
+                Ok(ptr) => unsafe { std::ptr::write(ptr.cast::<T>(), item) },
+                Err(e) => {
+                    unsafe {
+                        std::ptr::drop_in_place(std::ptr::slice_from_raw_parts_mut(start, len))
+                    };
+                    self.rewind(marker);
+                    return Err(e.into());
+                }
+            }
+            len += 1;
+        }
+        self.record(start.cast(), len * std::mem::size_of::<T>(), align);
+        let slice = std::ptr::slice_from_raw_parts_mut(start, len);
+        Ok(unsafe { NonNull::new_unchecked(slice) })
+    }
+
+    /// Stores each value in the arena and returns a slice of trait-object
+    /// references to them. Since Rust can't be generic over a trait, `coerce`
+    /// performs the unsizing, usually just `|v| v as &mut dyn Trait`.
+    ///
+    /// Values are stored without drop tracking, like `alloc`.
+    pub fn alloc_dyn_slice<'a, T: 'a, U: ?Sized + 'a>(
+        &'a mut self,
+        values: impl Iterator<Item = T>,
+        coerce: impl Fn(&'a mut T) -> &'a mut U,
+    ) -> anyhow::Result<&'a mut [&'a mut U]> {
+        let marker = self.marker();
+        let stored = self.collect_raw(values)?;
+        let ptr = stored.as_ptr() as *mut T;
+        let refs = (0..stored.len()).map(|i| coerce(unsafe { &mut *ptr.add(i) }));
+        match self.collect_raw(refs) {
+            Ok(refs) => Ok(unsafe { &mut *refs.as_ptr() }),
+            Err(e) => {
+                self.rewind(marker);
+                Err(e)
+            }
+        }
+    }
+
+    /// Stores `value` with its concrete destructor registered, as
+    /// `alloc_with_drop` does, and returns it as a trait object. `coerce`
+    /// performs the unsizing, usually just `|v| v as &mut dyn Trait`. Clearing
+    /// or dropping the arena destroys the value properly even though callers
+    /// only ever see the `dyn` reference.
+    pub fn alloc_dyn_boxed<'a, T: 'static, U: ?Sized + 'a>(
+        &'a mut self,
+        value: T,
+        coerce: impl FnOnce(&'a mut T) -> &'a mut U,
+    ) -> anyhow::Result<&'a mut U> {
+        let ptr = self.alloc_with_drop(value)?;
+        Ok(coerce(unsafe { &mut *ptr.ptr }))
+    }
+
+    /// Runs `f` with drop tracking suspended: `alloc_with_drop` calls made
+    /// inside it register no destructor. The caller asserts everything
+    /// allocated there is POD, or accepts that it leaks.
+    pub fn untracked_scope<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let tracking = std::mem::replace(&mut self.tracking, false);
+        let res = f(self);
+        self.tracking = tracking;
+        res
+    }
+
+    /// Whether both arenas hold allocations at the same offsets with the same
+    /// layouts, in the same order, whatever their base addresses.
+    #[cfg(feature = "debug-trace")]
+    pub fn same_layout(&self, other: &Self) -> bool {
+        self.trace == other.trace
+    }
+
+    /// Walks every live allocation, in allocation order, as its start address
+    /// and the layout it was bumped with, e.g. for the sweep phase of a
+    /// mark-sweep collector built on the arena.
+    #[cfg(feature = "debug-trace")]
+    pub fn sweep(&self) -> impl Iterator<Item = (NonNull<u8>, Layout)> + '_ {
+        self.trace.iter().map(|&(offset, layout)| {
+            (
+                unsafe { NonNull::new_unchecked(self.buf.add(offset)) },
+                layout,
+            )
+        })
+    }
+
+    /// Allocates `data` and labels its region for `memory_map`.
+    #[cfg(feature = "debug-trace")]
+    pub fn alloc_tagged<T>(&mut self, data: T, tag: &'static str) -> anyhow::Result<BumpPtr<T>> {
+        let ptr = self.alloc(data)?;
+        let offset = ptr.ptr as usize - self.buf as usize;
+        self.labels.push((offset, std::mem::size_of::<T>(), tag));
+        Ok(ptr)
+    }
+
+    /// `(offset, size, label)` of every live allocation made with
+    /// `alloc_tagged`, in allocation order.
+    #[cfg(feature = "debug-trace")]
+    pub fn memory_map(&self) -> Vec<(usize, usize, &'static str)> {
+        self.labels.clone()
+    }
+
+    /// Number of destructors currently registered.
+    pub fn tracked_drops(&self) -> usize {
+        self.drops.len()
+    }
+
+    /// How much work `clear` would do right now, judged by the number of
+    /// registered destructors and finalizers.
+    pub fn clear_cost(&self) -> ClearCost {
+        match self.drops.len() {
+            0 => ClearCost::Trivial,
+            n => ClearCost::Drops(n),
+        }
+    }
+
+    fn track<T>(&mut self, ptr: *mut T, run: Box<dyn FnOnce()>) {
+        let offset = ptr as usize - self.buf as usize;
+        let size = std::mem::size_of::<T>();
+        self.drops.push(DropEntry { offset, size, run });
+    }
+
+    /// Runs, in reverse allocation order, the destructors of every tracked
+    /// allocation at or above `offset`.
+    fn run_drops_from(&mut self, offset: usize) {
+        if self.drops.last().is_some_and(|e| e.offset >= offset) {
+            self.make_writable();
+        }
+        while self.drops.last().is_some_and(|e| e.offset >= offset) {
+            let entry = self.drops.pop().expect("checked above");
+            (entry.run)();
+        }
+    }
+
+    /// Allocates `data` and advances the cursor by `max(size_of::<T>(), stride)`,
+    /// so consecutive strided allocations of the same type form a regular grid.
+    /// `stride` must be a multiple of `align_of::<T>()`.
+    pub fn alloc_strided<T>(&mut self, data: T, stride: usize) -> anyhow::Result<BumpPtr<T>> {
+        if !stride.is_multiple_of(align_of::<T>()) {
+            bail!(
+                "BumpAllocator::alloc_strided => Stride must be a multiple of the type's alignment"
+            );
+        }
+        unsafe {
+            let advance = std::mem::size_of::<T>().max(stride);
+            let ptr = self.bump(advance, align_of::<T>())?.cast::<T>();
+            std::ptr::write(ptr, data);
+            self.tag_type::<T>(ptr.cast());
+
+            let sp = RadPtr { ptr };
+            Ok(sp)
+        }
+    }
+
+    /// Replaces the debug-build hook called with the type name whenever a
+    /// type that needs dropping goes through the untracked `alloc` path. The
+    /// default prints a warning to stderr. Release builds never call it.
+    pub fn set_needs_drop_hook(&mut self, hook: fn(&'static str)) {
+        #[cfg(debug_assertions)]
+        {
+            self.needs_drop_hook = hook;
+        }
+        #[cfg(not(debug_assertions))]
+        let _ = hook;
+    }
+
+    /// Caps the size of any single allocation, regardless of how much room is
+    /// left. Guards against corrupted or untrusted lengths driving huge
+    /// requests.
+    pub fn set_max_alloc(&mut self, bytes: usize) {
+        self.max_alloc = bytes;
+    }
+
+    /// Caps the total alignment padding the arena may insert before
+    /// allocations; one whose padding would push the total past `bytes` fails
+    /// with `AllocError::ExceedsMaxPadding`. Surfaces badly ordered allocation
+    /// patterns at runtime.
+    pub fn set_max_padding(&mut self, bytes: usize) {
+        self.max_padding = bytes;
+    }
+
+    /// Alignment padding inserted since the arena was last cleared or rewound
+    /// to 0. Partial rewinds don't reduce it.
+    pub fn padding(&self) -> usize {
+        self.padding
+    }
+
+    /// Makes the next `n` allocations fail with an out-of-memory error
+    /// regardless of free space, for exercising OOM handling in tests.
+    #[cfg(feature = "test-util")]
+    pub fn fail_next_allocs(&mut self, n: usize) {
+        self.fail_next = n;
+    }
+
+    /// Allocates `data` behind a `Guarded` handle that reports a leak if it is
+    /// dropped without `finish` being called; see `leaked_guards`.
+    #[cfg(feature = "test-util")]
+    pub fn alloc_guarded<T>(&mut self, data: T) -> anyhow::Result<Guarded<T>> {
+        let ptr = self.alloc(data)?;
+        Ok(Guarded {
+            ptr: Some(ptr),
+            type_name: std::any::type_name::<T>(),
+        })
+    }
+
+    /// Whether `alloc::<T>()` would succeed right now, accounting for the
+    /// alignment padding needed at the current cursor.
+    pub fn can_alloc<T>(&self) -> bool {
+        let (size, align) = self.uniform(std::mem::size_of::<T>(), align_of::<T>());
+        self.admits(size, align).is_ok()
+    }
+
+    /// Every check `bump` makes on a uniform-adjusted request, without
+    /// reserving anything or committing more pages. Returns the padding needed
+    /// at the cursor.
+    fn admits(&self, size: usize, align: usize) -> Result<usize, AllocError> {
+        if size > self.max_alloc {
+            return Err(AllocError::ExceedsMaxAlloc);
+        }
+        #[cfg(all(feature = "mmap", unix))]
+        if self.protected > 0 {
+            return Err(AllocError::ReadOnly);
+        }
+        #[cfg(feature = "test-util")]
+        if self.fail_next > 0 {
+            return Err(AllocError::OutOfMemory);
+        }
+        if !self.fits(size, align) {
+            return Err(AllocError::OutOfMemory);
+        }
+        let offset = unsafe { self.buf.add(self.size) }.align_offset(align);
+        if self.padding + offset > self.max_padding {
+            return Err(AllocError::ExceedsMaxPadding);
+        }
+        Ok(offset)
+    }
+
+    /// Lower bound on how many more `T`s fit, assuming the worst-case
+    /// alignment padding before the first one. Zero-sized types always fit.
+    pub fn min_capacity_for<T>(&self) -> usize {
+        let (size, align) = self.uniform(std::mem::size_of::<T>(), align_of::<T>());
+        match size {
+            0 => usize::MAX,
+            _ => self.remaining().saturating_sub(align - 1) / size,
+        }
+    }
+
+    /// Upper bound on how many more `T`s fit, assuming no alignment padding.
+    pub fn max_capacity_for<T>(&self) -> usize {
+        let (size, _) = self.uniform(std::mem::size_of::<T>(), align_of::<T>());
+        match size {
+            0 => usize::MAX,
+            _ => self.remaining() / size,
+        }
+    }
+
+    /// Alignment padding the next `alloc::<T>()` would insert at the current
+    /// cursor, including any uniform alignment.
+    pub fn next_padding<T>(&self) -> usize {
+        let (_, align) = self.uniform(std::mem::size_of::<T>(), align_of::<T>());
+        unsafe { self.buf.add(self.size) }.align_offset(align)
+    }
+
+    /// Whether `size` bytes aligned to `align` fit at the current cursor.
+    fn fits(&self, size: usize, align: usize) -> bool {
+        let (size, align) = self.uniform(size, align);
+        let offset = unsafe { self.buf.add(self.size) }.align_offset(align);
+        self.capacity - self.size >= size.saturating_add(offset)
+    }
+
+    /// Commits enough of a mapped arena's reservation to fit `extra` more
+    /// bytes past the cursor, at least doubling the committed size. The base
+    /// never moves. Always fails for other arenas.
+    #[cfg(all(feature = "mmap", target_os = "linux"))]
+    #[cold]
+    fn commit_more(&mut self, extra: usize) -> bool {
+        let BumpStorage::Mapped { reserved } = self.storage else {
+            return false;
+        };
+        let Some(needed) = self.size.checked_add(extra).filter(|&n| n <= reserved) else {
+            return false;
+        };
+        let target = needed
+            .max(self.capacity * 2)
+            .next_multiple_of(page_size())
+            .min(reserved);
+        let rc = unsafe {
+            libc::mprotect(
+                self.buf.add(self.capacity).cast(),
+                target - self.capacity,
+                libc::PROT_READ | libc::PROT_WRITE,
+            )
+        };
+        if rc != 0 {
+            return false;
+        }
+        self.capacity = target;
+        true
+    }
+
+    #[cfg(not(all(feature = "mmap", target_os = "linux")))]
+    #[inline(always)]
+    fn commit_more(&mut self, _extra: usize) -> bool {
+        false
+    }
+
+    /// Applies the arena's uniform alignment to a request.
+    fn uniform(&self, size: usize, align: usize) -> (usize, usize) {
+        let size = size.next_multiple_of(self.uniform_align);
+        (size, align.max(self.uniform_align))
+    }
+
+    /// Reserves `size` bytes aligned to `align`, accounting for the padding
+    /// needed at the current cursor, and returns a pointer to the start.
+    #[inline]
+    fn bump(&mut self, size: usize, align: usize) -> Result<*mut u8, AllocError> {
+        let (size, align) = self.uniform(size, align);
+        let ptr = self.reserve(size, align)?;
+        self.record(ptr, size, align);
+        Ok(ptr)
+    }
+
+    /// `bump` without the uniform alignment or the bookkeeping, for building
+    /// one allocation out of several reservations; `record` it once done.
+    #[inline]
+    fn reserve(&mut self, size: usize, align: usize) -> Result<*mut u8, AllocError> {
+        #[cfg(feature = "test-util")]
+        if self.fail_next > 0 {
+            self.fail_next -= 1;
+            return Err(AllocError::OutOfMemory);
+        }
+        unsafe {
+            let ptr = self.buf.add(self.size);
+            let offset = match self.admits(size, align) {
+                Err(AllocError::OutOfMemory)
+                    if self.commit_more(size.saturating_add(ptr.align_offset(align))) =>
+                {
+                    self.admits(size, align)?
+                }
+                res => res?,
+            };
+            self.padding += offset;
+            self.size += size + offset;
+            self.peak = self.peak.max(self.size);
+            Ok(ptr.add(offset))
+        }
+    }
+
+    /// Counts a finished allocation of `size` bytes at `ptr` and, with
+    /// `debug-trace`, logs its layout.
+    #[inline]
+    #[cfg_attr(not(feature = "debug-trace"), allow(unused_variables))]
+    fn record(&mut self, ptr: *mut u8, size: usize, align: usize) {
+        #[cfg(feature = "debug-trace")]
+        self.trace.push((ptr as usize - self.buf as usize, unsafe {
+            Layout::from_size_align_unchecked(size, align)
+        }));
+        self.alloc_count += 1;
+    }
+
+    /// Allocates `data` and also returns the marker taken just before it, so
+    /// `rewind(marker)` frees exactly this allocation and everything after.
+    pub fn alloc_resettable<T>(&mut self, data: T) -> anyhow::Result<(BumpPtr<T>, usize)> {
+        let marker = self.marker();
+        let ptr = self.alloc(data)?;
+        Ok((ptr, marker))
+    }
+
+    /// Current position of the bump cursor, to be passed back to `rewind`.
+    pub fn marker(&self) -> usize {
+        self.size
+    }
+
+    /// Bytes `rewind(marker)` would give back, padding included. A marker
+    /// past the cursor reclaims nothing.
+    pub fn reclaim_size(&self, marker: usize) -> usize {
+        debug_assert!(
+            marker <= self.size,
+            "BumpAllocator::reclaim_size => Marker is past the current cursor"
+        );
+        self.size.saturating_sub(marker)
+    }
+
+    /// Frees every allocation made since `marker` was taken. Destructors
+    /// registered at offsets `>= marker` run in reverse order and are removed;
+    /// those below the marker are untouched.
+    pub fn rewind(&mut self, marker: usize) {
+        debug_assert!(
+            marker <= self.size,
+            "BumpAllocator::rewind => Marker is past the current cursor"
+        );
+        let marker = marker.min(self.size);
+        self.run_drops_from(marker);
+        self.reset_cursor(marker);
+    }
+
+    /// Moves the cursor back to `marker` without running any destructors.
+    fn reset_cursor(&mut self, marker: usize) {
+        let marker = marker.min(self.size);
+        #[cfg(all(debug_assertions, feature = "debug-trace"))]
+        if let Some(tags) = &mut self.type_tags {
+            tags.retain(|&(offset, ..)| offset < marker);
+        }
+        #[cfg(feature = "debug-trace")]
+        {
+            self.trace.retain(|&(offset, _)| offset < marker);
+            self.labels.retain(|&(offset, _, _)| offset < marker);
+        }
+        if marker == 0 {
+            self.alloc_count = 0;
+            self.padding = 0;
+        }
+        self.size = marker;
+    }
+
+    /// Lends all the unallocated space to `f` as a scratch buffer. The space
+    /// is zeroed first and stays unallocated, so anything written there is
+    /// reclaimed as soon as `f` returns.
+    pub fn with_remaining<R>(&mut self, f: impl FnOnce(&mut [u8]) -> R) -> R {
+        self.make_writable();
+        let remaining = unsafe {
+            let start = self.buf.add(self.size);
+            let len = self.capacity - self.size;
+            std::ptr::write_bytes(start, 0, len);
+            std::slice::from_raw_parts_mut(start, len)
+        };
+        f(remaining)
+    }
+
+    /// Runs `f` against the allocator and, if it returns an error, rewinds
+    /// every allocation it made so no partial object is left behind.
+    ///
+    /// On rollback, exactly the destructors registered during `f` run, once,
+    /// in reverse order, and are removed so a later clear or drop never runs
+    /// them again. Destructors registered before the transaction are left
+    /// alone, even for zero-sized values sitting right at its start.
+    pub fn transaction<R>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> anyhow::Result<R>,
+    ) -> anyhow::Result<R> {
+        let (marker, tracked) = (self.marker(), self.drops.len());
+        let res = f(self);
+        if res.is_err() {
+            self.make_writable();
+            while self.drops.len() > tracked {
+                let entry = self.drops.pop().expect("checked above");
+                (entry.run)();
+            }
+            self.reset_cursor(marker);
+        }
+        res
+    }
+
+    /// Frees everything and starts a new epoch, invalidating every
+    /// `EpochOffset` stamped before the clear.
+    /// Freed bytes are then left, poisoned or zeroed per the `ClearPolicy`.
+    pub fn clear(&mut self) {
+        self.make_writable();
+        let used = self.size;
+        self.rewind(0);
+        match self.clear_policy {
+            ClearPolicy::Fast => {}
+            ClearPolicy::Poison => unsafe { std::ptr::write_bytes(self.buf, POISON_BYTE, used) },
+            ClearPolicy::Zero => zero_bytes(self.buf, used),
+        }
+        self.epoch = self.epoch.wrapping_add(1);
+    }
+
+    pub fn release(self) {
+        drop(self)
+    }
+
+    /// Marks the pages holding the allocated region read-only, so any stray
+    /// write into a finished structure faults instead of corrupting it. New
+    /// allocations fail with `AllocError::ReadOnly` until `unprotect`. Methods
+    /// that write to existing memory, such as `clear`, `with_remaining`,
+    /// `restore` or a rewind that runs destructors, unprotect the arena first.
+    ///
+    /// The buffer must start on a page boundary and the used region, rounded
+    /// up to whole pages, must lie within the arena, so no memory outside it
+    /// is locked. Arenas from `new_mremap`, or from `with_align` with a
+    /// page-multiple size and page alignment, qualify.
+    #[cfg(all(feature = "mmap", unix))]
+    pub fn protect_readonly(&mut self) -> anyhow::Result<()> {
+        let page = page_size();
+        let len = self.size.next_multiple_of(page);
+        if !(self.buf as usize).is_multiple_of(page) || len > self.capacity {
+            bail!("BumpAllocator::protect_readonly => Used region does not cover whole pages owned by the arena");
+        }
+        self.unprotect();
+        if len == 0 {
+            return Ok(());
+        }
+        if unsafe { libc::mprotect(self.buf.cast(), len, libc::PROT_READ) } != 0 {
+            bail!("BumpAllocator::protect_readonly => mprotect failed");
+        }
+        self.protected = len;
+        Ok(())
+    }
+
+    /// Makes pages locked by `protect_readonly` writable again. Does nothing
+    /// if the arena is not protected.
+    #[cfg(all(feature = "mmap", unix))]
+    pub fn unprotect(&mut self) {
+        if self.protected == 0 {
+            return;
+        }
+        unsafe {
+            libc::mprotect(
+                self.buf.cast(),
+                self.protected,
+                libc::PROT_READ | libc::PROT_WRITE,
+            )
+        };
+        self.protected = 0;
+    }
+
+    /// Unprotects the arena ahead of a write to memory it already holds.
+    #[inline]
+    fn make_writable(&mut self) {
+        #[cfg(all(feature = "mmap", unix))]
+        self.unprotect();
+    }
+}
+
+/// Pointer stored as the distance from its own address to the target, so a
+/// structure built from them stays valid when the whole arena blob is copied
+/// or mapped somewhere else. Moving a `RelPtr` on its own breaks it, which is
+/// why it is neither `Clone` nor `Copy`; it should only live inside arena
+/// memory alongside its target.
+#[repr(transparent)]
+pub struct RelPtr<T> {
+    /// `target - self`, with `isize::MIN` meaning null: 0 is a real offset,
+    /// for a `RelPtr` at the start of the value it points to.
+    offset: isize,
+    _marker: PhantomData<*const T>,
+}
+
+impl<T> RelPtr<T> {
+    const NULL: isize = isize::MIN;
+
+    pub const fn null() -> Self {
+        Self {
+            offset: Self::NULL,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.offset == Self::NULL
+    }
+
+    /// Points `self` at `target`, which must sit in the same arena.
+    pub fn set(&mut self, target: &T) {
+        self.offset = (target as *const T as isize).wrapping_sub(self as *const Self as isize);
+    }
+
+    /// The target, found from `self`'s current position in `arena` and read
+    /// through the arena's own buffer.
+    ///
+    /// Panics if the pointer is null, or if it or its target doesn't lie in
+    /// `arena`'s allocated region.
+    ///
+    /// # Safety
+    /// The target bytes must hold an initialized `T`. Only the bounds and
+    /// alignment are checked, so a `RelPtr` that was moved after `set`, or
+    /// whose target was overwritten, resolves to arbitrary bytes.
+    pub unsafe fn get<'a>(&self, arena: &'a BumpAllocator) -> &'a T {
+        assert!(
+            !self.is_null(),
+            "RelPtr::get => Attempted to dereference null RelPtr"
+        );
+        let base = arena.buf as usize;
+        let target = (self as *const Self as usize)
+            .checked_sub(base)
+            .filter(|&here| here < arena.used())
+            .and_then(|here| here.checked_add_signed(self.offset))
+            .filter(|&target| {
+                target
+                    .checked_add(std::mem::size_of::<T>())
+                    .is_some_and(|end| end <= arena.used())
+            })
+            .filter(|&target| (base + target).is_multiple_of(align_of::<T>()))
+            .expect("RelPtr::get => Pointer or target lies outside the arena");
+        unsafe { &*arena.buf.add(target).cast::<T>() }
+    }
+}
+
+impl<T> Default for RelPtr<T> {
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+impl<T> fmt::Debug for RelPtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RelPtr").field(&self.offset).finish()
+    }
+}
+
+/// Markers captured on several arenas at once, for rolling back work that
+/// spans them, e.g. an AST in one arena and scratch data in another. Arenas
+/// are matched up by `id`, so they may be passed back in any order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiMarker {
+    /// `(arena id, marker)` for each captured arena.
+    markers: Vec<(u64, usize)>,
+}
+
+impl MultiMarker {
+    pub fn capture(arenas: &[&BumpAllocator]) -> Self {
+        Self {
+            markers: arenas.iter().map(|a| (a.id(), a.marker())).collect(),
+        }
+    }
+
+    /// Rewinds every arena to its captured marker. Fails without touching any
+    /// of them unless `arenas` is exactly the captured set and each cursor is
+    /// still at or past its marker.
+    pub fn rewind_all(&self, arenas: &mut [&mut BumpAllocator]) -> anyhow::Result<()> {
+        if arenas.len() != self.markers.len() {
+            bail!("MultiMarker::rewind_all => Arenas do not match the captured set");
+        }
+        let mut targets = Vec::with_capacity(arenas.len());
+        for arena in arenas.iter() {
+            let Some(&(_, marker)) = self.markers.iter().find(|(id, _)| *id == arena.id()) else {
+                bail!("MultiMarker::rewind_all => Arenas do not match the captured set");
+            };
+            if marker > arena.marker() {
+                bail!("MultiMarker::rewind_all => Arena was rewound past its captured marker");
+            }
+            targets.push(marker);
+        }
+        for (arena, marker) in arenas.iter_mut().zip(targets) {
+            arena.rewind(marker);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "test-util")]
+thread_local! {
+    static LEAKED_GUARDS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Number of `Guarded` handles dropped on this thread without `finish`.
+#[cfg(feature = "test-util")]
+pub fn leaked_guards() -> usize {
+    LEAKED_GUARDS.with(Cell::get)
+}
+
+/// Leak-detecting handle from `BumpAllocator::alloc_guarded`, for catching
+/// forgotten cleanups in tests. Dropping it without calling `finish` prints a
+/// warning and counts towards `leaked_guards`.
+#[cfg(feature = "test-util")]
+#[must_use = "dropping a Guarded handle without calling `finish` is reported as a leak"]
+pub struct Guarded<T> {
+    ptr: Option<BumpPtr<T>>,
+    type_name: &'static str,
+}
+
+#[cfg(feature = "test-util")]
+impl<T> Guarded<T> {
+    /// Marks the handle as properly consumed and returns the plain pointer.
+    pub fn finish(mut self) -> BumpPtr<T> {
+        self.ptr
+            .take()
+            .expect("Guarded::finish => Handle already finished")
+    }
+}
+
+#[cfg(feature = "test-util")]
+impl<T> Deref for Guarded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.ptr
+            .as_ref()
+            .expect("Guarded::deref => Handle already finished")
+    }
+}
+
+#[cfg(feature = "test-util")]
+impl<T> Drop for Guarded<T> {
+    fn drop(&mut self) {
+        if self.ptr.is_some() {
+            eprintln!(
+                "zen_alloc: warning: `Guarded<{}>` dropped without `finish`",
+                self.type_name
+            );
+            LEAKED_GUARDS.with(|n| n.set(n.get() + 1));
+        }
+    }
+}
+
+/// Byte offset into a `BumpAllocator`, stamped with the arena's epoch so it
+/// stops resolving once the arena is cleared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EpochOffset {
+    pub offset: usize,
+    pub epoch: u32,
+}
+
+/// `EpochOffset` that remembers the type allocated there, from
+/// `alloc_offset`. It holds no pointer, so it stays meaningful across
+/// `snapshot`/`restore` and can be stored or serialized alongside the
+/// arena's bytes.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct OffsetHandle<T> {
+    raw: EpochOffset,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Clone for OffsetHandle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for OffsetHandle<T> {}
+
+impl<T> OffsetHandle<T> {
+    /// The untyped offset and epoch.
+    pub fn raw(&self) -> EpochOffset {
+        self.raw
+    }
+
+    /// The value in `arena`, or `None` if the arena's epoch has moved on
+    /// since the handle was made or the offset is out of bounds.
+    ///
+    /// # Safety
+    /// `arena` must be the arena the handle came from, or one restored from
+    /// its snapshot, and must not have been rewound below the handle since.
+    /// Neither changes the epoch, so neither is caught.
+    pub unsafe fn resolve<'a>(&self, arena: &'a BumpAllocator) -> Option<&'a T> {
+        let ptr = arena.resolve::<T>(self.raw).ok()?;
+        Some(&*ptr.ptr)
+    }
+}
+
+/// Used bytes and epoch of a `BumpAllocator`; see `BumpAllocator::snapshot`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BumpSnapshot {
+    pub bytes: Vec<u8>,
+    /// Alignment of the source arena's base, which offsets rely on.
+    pub align: usize,
+    pub epoch: u32,
+}
+
+/// Reference to an arena allocation whose address is its identity. The value
+/// never moves for as long as the reference is alive, and the borrow stops
+/// the arena from being cleared or rewound underneath it. Equality and
+/// hashing compare addresses, never values.
+pub struct StableRef<'a, T>(&'a T);
+
+impl<T> StableRef<'_, T> {
+    pub fn as_ptr(&self) -> *const T {
+        self.0
+    }
+}
+
+impl<T> Clone for StableRef<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for StableRef<'_, T> {}
+
+impl<T> PartialEq for StableRef<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.0, other.0)
+    }
+}
+
+impl<T> Eq for StableRef<'_, T> {}
+
+impl<T> Hash for StableRef<'_, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_ptr().hash(state);
+    }
+}
+
+impl<T> Deref for StableRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for StableRef<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("StableRef")
+            .field(&self.as_ptr())
+            .field(self.0)
+            .finish()
+    }
+}
+
+/// Shared, read-only handle to an arena allocation. Copies can be passed
+/// around freely since none of them can ever mutate the value.
+pub struct ArenaShared<'a, T>(&'a T);
+
+impl<T> Clone for ArenaShared<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ArenaShared<'_, T> {}
+
+impl<T> Deref for ArenaShared<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.0
+    }
+}
+
+/// A field in a `Schema`: where a value lives in an arena and its layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchemaField {
+    pub offset: usize,
+    pub size: usize,
+    pub align: usize,
+}
+
+/// Record layout recorded while writing into an arena, used to read the
+/// fields back with `BumpAllocator::visit`.
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    fields: Vec<SchemaField>,
+}
+
+impl Schema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, offset: usize, size: usize, align: usize) {
+        self.fields.push(SchemaField {
+            offset,
+            size,
+            align,
+        });
+    }
+
+    /// Records a field holding a `T` at `offset`.
+    pub fn field<T>(&mut self, offset: usize) {
+        self.push(offset, std::mem::size_of::<T>(), align_of::<T>());
+    }
+
+    pub fn fields(&self) -> &[SchemaField] {
+        &self.fields
+    }
+}
+
+impl Clone for BumpAllocator {
+    /// Panics if the global allocator cannot provide the new buffer; see
+    /// `try_clone` for the fallible version.
+    fn clone(&self) -> Self {
+        self.try_clone()
+            .expect("BumpAllocator::clone => Unable to allocate buffer for clone")
+    }
+}
+
+impl Drop for BumpAllocator {
+    fn drop(&mut self) {
+        #[cfg(all(feature = "mmap", unix))]
+        self.unprotect();
+        self.run_drops_from(0);
+        match self.storage {
+            BumpStorage::Global => unsafe { dealloc(self.buf, self.layout) },
+            BumpStorage::Parent => {}
+            #[cfg(all(feature = "mmap", target_os = "linux"))]
+            BumpStorage::Mapped { reserved } => unsafe {
+                libc::munmap(self.buf.cast(), reserved);
+            },
+            #[cfg(all(feature = "mmap", unix))]
+            BumpStorage::Guarded => unsafe {
+                libc::munmap(self.buf.cast(), self.capacity + page_size());
+            },
+        }
+    }
+}
+
+/// One-line summary for log output, e.g.
+/// `BumpAllocator(used=1234/4096 bytes, 30% full, 12 allocs)`.
+impl fmt::Display for BumpAllocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "BumpAllocator(used={}/{} bytes, {}% full, {} allocs)",
+            self.used(),
+            self.capacity(),
+            percent(self.used(), self.capacity()),
+            self.allocation_count()
+        )
+    }
+}
+
+/// Slice allocated by `BumpAllocator::alloc_slice`, keeping its pointer and
+/// length together. Derefs to `[T]`.
+pub struct ArenaSlice<'a, T> {
+    ptr: NonNull<T>,
+    len: usize,
+    _arena: PhantomData<&'a mut [T]>,
+}
+
+impl<'a, T> ArenaSlice<'a, T> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The plain slice, borrowing the arena for as long as the handle did.
+    pub fn into_slice(self) -> &'a mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> Deref for ArenaSlice<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> DerefMut for ArenaSlice<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ArenaSlice<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+/// Row-major 2D view over a grid allocated by `BumpAllocator::alloc_matrix`.
+/// Indexing with `[(row, col)]` panics out of bounds; `get` and `get_mut`
+/// return `None` instead.
+pub struct MatrixView<'a, T> {
+    data: &'a mut [T],
+    rows: usize,
+    cols: usize,
+}
+
+impl<T> MatrixView<'_, T> {
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> Option<&T> {
+        (row < self.rows && col < self.cols).then(|| &self.data[row * self.cols + col])
+    }
+
+    pub fn get_mut(&mut self, row: usize, col: usize) -> Option<&mut T> {
+        (row < self.rows && col < self.cols).then(|| &mut self.data[row * self.cols + col])
+    }
+
+    /// The elements of `row`, left to right.
+    pub fn row(&self, row: usize) -> &[T] {
+        &self.data[row * self.cols..(row + 1) * self.cols]
+    }
+
+    /// Every element in row-major order.
+    pub fn as_slice(&self) -> &[T] {
+        self.data
+    }
+}
+
+impl<T> Index<(usize, usize)> for MatrixView<'_, T> {
+    type Output = T;
+
+    fn index(&self, (row, col): (usize, usize)) -> &Self::Output {
+        self.get(row, col)
+            .expect("MatrixView::index => Index out of bounds")
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for MatrixView<'_, T> {
+    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut Self::Output {
+        self.get_mut(row, col)
+            .expect("MatrixView::index_mut => Index out of bounds")
+    }
+}
+
+/// Sequential `std::io::Write` cursor over a region reserved in a
+/// `BumpAllocator`, for serializing straight into the arena. Writes past the
+/// reserved capacity are cut short. Any unwritten tail stays reserved.
+pub struct ArenaWriter<'a> {
+    buf: *mut u8,
+    len: usize,
+    capacity: usize,
+    _arena: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> ArenaWriter<'a> {
+    /// Bytes written so far.
+    pub fn written(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.buf, self.len) }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.capacity - self.len
+    }
+
+    /// Ends writing and returns everything written, borrowed from the arena.
+    pub fn finish(self) -> &'a [u8] {
+        unsafe { std::slice::from_raw_parts(self.buf, self.len) }
+    }
+}
+
+impl std::io::Write for ArenaWriter<'_> {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        let n = data.len().min(self.remaining());
+        unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), self.buf.add(self.len), n) };
+        self.len += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Region of up to `max` bytes at the top of a `BumpAllocator`, filled with
+/// `write` and trimmed to what was written by `finish`. Holding the arena
+/// borrowed keeps the region on top until then.
+pub struct RegionBuilder<'a> {
+    arena: &'a mut BumpAllocator,
+    start: *mut u8,
+    len: usize,
+    max: usize,
+}
+
+impl<'a> RegionBuilder<'a> {
+    /// Appends `bytes`, failing if they don't fit in what is left of the region.
+    pub fn write(&mut self, bytes: &[u8]) -> anyhow::Result<()> {
+        if bytes.len() > self.max - self.len {
+            bail!("RegionBuilder::write => Write exceeds the reserved region");
+        }
+        unsafe {
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), self.start.add(self.len), bytes.len())
+        };
+        self.len += bytes.len();
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the written bytes and gives the unwritten tail back to the arena.
+    pub fn finish(self) -> &'a [u8] {
+        // The borrow has kept the region on top, and it isn't drop-tracked.
+        // Everything above `start` goes, including any rounding past `max`
+        // from uniform alignment.
+        let offset = self.start as usize - self.arena.buf as usize;
+        self.arena.truncate_top(offset, self.len);
+        unsafe { std::slice::from_raw_parts(self.start, self.len) }
+    }
+}
+
+/// Uninitialized arena space for a `T`, for building large values in place
+/// instead of on the caller's stack. Write each field through `as_mut_ptr`
+/// (e.g. with `addr_of_mut!`), then call `finish`. Dropping the builder
+/// without finishing leaves the space reserved but unused.
+pub struct UninitBuilder<'a, T> {
+    ptr: NonNull<T>,
+    _arena: PhantomData<&'a mut T>,
+}
+
+impl<T> UninitBuilder<'_, T> {
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.ptr.as_ptr()
+    }
+
+    /// Returns a pointer to the finished value.
+    ///
+    /// # Safety
+    ///
+    /// Every field of the `T` must have been initialized.
+    pub unsafe fn finish(self) -> RadPtr<T> {
+        RadPtr {
+            ptr: self.ptr.as_ptr(),
+        }
+    }
+}
+
+/// Fixed-capacity vector backed by arena memory; see
+/// `BumpAllocator::alloc_vec`. Its elements are dropped with the vector, but
+/// the memory stays reserved in the arena.
+pub struct ArenaVec<'a, T> {
+    ptr: NonNull<T>,
+    len: usize,
+    capacity: usize,
+    _arena: PhantomData<&'a mut [T]>,
+}
+
+impl<T> ArenaVec<'_, T> {
+    /// Appends `value`, failing if the vector is already at capacity.
+    pub fn push(&mut self, value: T) -> anyhow::Result<()> {
+        if self.len == self.capacity {
+            bail!("ArenaVec::push => Vector is at capacity");
+        }
+        unsafe { self.ptr.as_ptr().add(self.len).write(value) };
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> Deref for ArenaVec<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+
+impl<T> DerefMut for ArenaVec<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.as_mut_slice()
+    }
+}
+
+impl<T> Drop for ArenaVec<'_, T> {
+    fn drop(&mut self) {
+        unsafe { std::ptr::drop_in_place(self.as_mut_slice()) }
+    }
+}
+
+/// A child arena over `bytes` reserved from a parent `BumpAllocator`. The
+/// parent stays borrowed while the child is alive; the child's drops run when
+/// it is dropped, and its bytes stay reserved in the parent until the parent
+/// is rewound or cleared.
+///
+/// Only a forwarding subset of the mutable API is exposed, since a `&mut`
+/// to the inner arena could be swapped out to outlive the parent.
+pub struct SubArena<'a> {
+    bump: BumpAllocator,
+    _parent: PhantomData<&'a mut BumpAllocator>,
+}
+
+impl SubArena<'_> {
+    pub fn alloc<T>(&mut self, data: T) -> anyhow::Result<BumpPtr<T>> {
+        self.bump.alloc(data)
+    }
+
+    pub fn alloc_layout(&mut self, layout: Layout) -> anyhow::Result<NonNull<u8>> {
+        self.bump.alloc_layout(layout)
+    }
+
+    pub fn alloc_with_drop<T: 'static>(&mut self, data: T) -> anyhow::Result<BumpPtr<T>> {
+        self.bump.alloc_with_drop(data)
+    }
+
+    pub fn rewind(&mut self, marker: usize) {
+        self.bump.rewind(marker)
+    }
+
+    pub fn clear(&mut self) {
+        self.bump.clear()
+    }
+}
+
+impl Deref for SubArena<'_> {
+    type Target = BumpAllocator;
+
+    fn deref(&self) -> &Self::Target {
+        &self.bump
+    }
+}
+
+/// Bump arena that allocates from the top of its buffer downward. A downward
+/// cursor needs only a subtraction and a mask per allocation, no separate
+/// padding computation. Values are never dropped by the arena.
+pub struct DownBumpAllocator {
+    buf: *mut u8,
+    layout: Layout,
+    /// Address of the lowest allocated byte; starts one past the buffer end.
+    cursor: usize,
+}
+
+impl DownBumpAllocator {
+    pub fn new(size_bytes: usize) -> anyhow::Result<Self> {
+        Self::with_align(size_bytes, BumpAllocator::DEFAULT_ALIGNMENT)
+    }
+
+    pub fn with_align(size_bytes: usize, align: usize) -> anyhow::Result<Self> {
+        let layout = Layout::from_size_align(size_bytes, align)?;
+        let buf = unsafe { alloc(layout) };
+        if buf.is_null() {
+            bail!("DownBumpAllocator::with_align => Unable to allocate more memory from Global Allocator");
+        }
+        let s = Self {
+            buf,
+            layout,
+            cursor: buf as usize + size_bytes,
+        };
+        Ok(s)
+    }
+
+    pub fn alloc<T>(&mut self, data: T) -> anyhow::Result<RadPtr<T>> {
+        self.alloc_aligned(data, align_of::<T>())
+    }
+
+    /// Allocates `data` below the cursor at an address aligned to `align` (or
+    /// `T`'s own alignment, if larger). The cursor is rounded down, never up,
+    /// and the allocation fails if that would take it below the buffer start.
+    pub fn alloc_aligned<T>(&mut self, data: T, align: usize) -> anyhow::Result<RadPtr<T>> {
+        if !align.is_power_of_two() {
+            bail!("DownBumpAllocator::alloc_aligned => Alignment must be a power of two");
+        }
+        let align = align.max(align_of::<T>());
+        let addr = self
+            .cursor
+            .checked_sub(std::mem::size_of::<T>())
+            .map(|addr| addr & !(align - 1))
+            .filter(|&addr| addr >= self.buf as usize);
+        let Some(addr) = addr else {
+            bail!("DownBumpAllocator::alloc_aligned => Cannot perform allocation: Allocator out of memory");
+        };
+        self.cursor = addr;
+
+        let ptr = self.buf.wrapping_add(addr - self.buf as usize).cast::<T>();
+        unsafe { std::ptr::write(ptr, data) };
+        Ok(RadPtr { ptr })
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.layout.size()
+    }
+
+    /// Bytes between the cursor and the end of the buffer, padding included.
+    pub fn used(&self) -> usize {
+        self.buf as usize + self.capacity() - self.cursor
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.cursor - self.buf as usize
+    }
+
+    pub fn clear(&mut self) {
+        self.cursor = self.buf as usize + self.capacity();
+    }
+}
+
+impl Drop for DownBumpAllocator {
+    fn drop(&mut self) {
+        unsafe { dealloc(self.buf, self.layout) }
+    }
+}
+
+/// Bump arena holding values of a single type, laid out back to back so the
+/// `n`th allocation can be reached by index. Values are dropped when the
+/// arena is cleared or dropped.
+pub struct TypedBump<T> {
+    bump: BumpAllocator,
+    len: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T> TypedBump<T> {
+    /// Creates an arena with room for `capacity` values.
+    pub fn new(capacity: usize) -> anyhow::Result<Self> {
+        let layout = Layout::array::<T>(capacity)?;
+        let s = Self {
+            bump: BumpAllocator::with_align(layout.size(), layout.align())?,
+            len: 0,
+            _marker: PhantomData,
+        };
+        Ok(s)
+    }
+
+    pub fn alloc(&mut self, data: T) -> anyhow::Result<BumpPtr<T>> {
+        let ptr = self.bump.write(data)?;
+        self.len += 1;
+        Ok(ptr)
+    }
+
+    /// Allocates `data` and returns its index, a 4-byte handle to resolve
+    /// with `get` in place of an 8-byte pointer.
+    pub fn push(&mut self, data: T) -> anyhow::Result<u32> {
+        let Ok(index) = u32::try_from(self.len) else {
+            bail!("TypedBump::push => Index no longer fits in a u32 handle");
+        };
+        self.alloc(data)?;
+        Ok(index)
+    }
+
+    /// The value at an index returned by `push`, if it is still allocated.
+    pub fn get(&self, index: u32) -> Option<&T> {
+        self.as_slice().get(index as usize)
+    }
+
+    /// Number of values allocated so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.bump.capacity() / std::mem::size_of::<T>().max(1)
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.bump.buf.cast::<T>(), self.len) }
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(self.bump.buf.cast::<T>(), self.len) }
+    }
+
+    pub fn backing(&self) -> Backing {
+        self.bump.backing()
+    }
+
+    /// Drops every value and resets the arena. The arena is emptied before
+    /// any destructor runs, so one that panics can't cause a double drop.
+    pub fn clear(&mut self) {
+        let live = std::ptr::slice_from_raw_parts_mut(self.bump.buf.cast::<T>(), self.len);
+        self.len = 0;
+        // The inner arena clears with `ClearPolicy::Fast`, leaving the values
+        // in place to be dropped.
+        self.bump.clear();
+        unsafe { std::ptr::drop_in_place(live) };
+    }
+}
+
+impl<T> Index<usize> for TypedBump<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.as_slice()[index]
+    }
+}
+
+impl<T> IndexMut<usize> for TypedBump<T> {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        &mut self.as_mut_slice()[index]
+    }
+}
+
+impl<T> Drop for TypedBump<T> {
+    fn drop(&mut self) {
+        unsafe { std::ptr::drop_in_place(self.as_mut_slice()) };
+    }
+}
+
+/// Shared cap on the total bytes a group of growable arenas may request from
+/// the global allocator. Clones refer to the same budget.
+#[derive(Debug, Clone)]
+pub struct MemoryBudget {
+    inner: Arc<BudgetInner>,
+}
+
+#[derive(Debug)]
+struct BudgetInner {
+    used: AtomicUsize,
+    max: usize,
+}
+
+impl MemoryBudget {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            inner: Arc::new(BudgetInner {
+                used: AtomicUsize::new(0),
+                max: max_bytes,
+            }),
+        }
+    }
+
+    pub fn used(&self) -> usize {
+        self.inner.used.load(Ordering::Acquire)
+    }
+
+    pub fn max(&self) -> usize {
+        self.inner.max
+    }
+
+    /// Claims `bytes` from the budget, returning false if that would exceed it.
+    fn try_reserve(&self, bytes: usize) -> bool {
+        self.inner
+            .used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                used.checked_add(bytes)
+                    .filter(|&total| total <= self.inner.max)
+            })
+            .is_ok()
+    }
+
+    fn release(&self, bytes: usize) {
+        self.inner.used.fetch_sub(bytes, Ordering::AcqRel);
+    }
+}
+
+/// A bump arena that grows by chaining additional `BumpAllocator` chunks
+/// instead of failing when the current chunk is full. Pointers stay valid
+/// across growth since existing chunks never move.
+pub struct ChunkedBumpAllocator {
+    chunks: Vec<BumpAllocator>,
+    chunk_size: usize,
+    align: usize,
+    budget: Option<MemoryBudget>,
+}
+
+impl ChunkedBumpAllocator {
+    pub fn new(chunk_size: usize) -> anyhow::Result<Self> {
+        Self::create(chunk_size, BumpAllocator::DEFAULT_ALIGNMENT, None)
+    }
+
+    pub fn with_align(chunk_size: usize, align: usize) -> anyhow::Result<Self> {
+        Self::create(chunk_size, align, None)
+    }
+
+    /// Creates an arena whose chunks are all charged against `budget`.
+    pub fn with_budget(chunk_size: usize, budget: &MemoryBudget) -> anyhow::Result<Self> {
+        Self::create(
+            chunk_size,
+            BumpAllocator::DEFAULT_ALIGNMENT,
+            Some(budget.clone()),
+        )
+    }
+
+    fn create(
+        chunk_size: usize,
+        align: usize,
+        budget: Option<MemoryBudget>,
+    ) -> anyhow::Result<Self> {
+        let mut s = Self {
+            chunks: Vec::new(),
+            chunk_size,
+            align,
+            budget,
+        };
+        s.grow(chunk_size)?;
+        Ok(s)
+    }
+
+    pub fn alloc<T>(&mut self, data: T) -> anyhow::Result<BumpPtr<T>> {
+        let (size, align) = (std::mem::size_of::<T>(), align_of::<T>());
+        let fits = self
+            .chunks
+            .last()
+            .is_some_and(|chunk| chunk.fits(size, align));
+        if !fits {
+            self.grow(self.chunk_size.max(size + align))?;
+        }
+        let chunk = self
+            .chunks
+            .last_mut()
+            .expect("ChunkedBumpAllocator always holds at least one chunk");
+        chunk.alloc(data)
+    }
+
+    /// Total bytes requested from the global allocator across all chunks.
+    pub fn capacity(&self) -> usize {
+        self.chunks.iter().map(|c| c.capacity()).sum()
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn backing(&self) -> Backing {
+        Backing::Heap
+    }
+
+    /// Frees every chunk but the first, crediting their bytes back to the
+    /// `MemoryBudget`, and resets the first for reuse. Chunks are dropped
+    /// newest first, so destructors still run in reverse allocation order.
+    pub fn clear(&mut self) {
+        while self.chunks.len() > 1 {
+            let chunk = self.chunks.pop().expect("checked above");
+            if let Some(budget) = &self.budget {
+                budget.release(chunk.capacity);
+            }
+        }
+        if let Some(first) = self.chunks.first_mut() {
+            first.clear();
+        }
+    }
+
+    fn grow(&mut self, bytes: usize) -> anyhow::Result<()> {
+        if let Some(budget) = &self.budget {
+            if !budget.try_reserve(bytes) {
+                bail!("ChunkedBumpAllocator::grow => Memory budget exhausted");
+            }
+        }
+        match BumpAllocator::with_align(bytes, self.align) {
+            Ok(chunk) => {
+                self.chunks.push(chunk);
+                Ok(())
+            }
+            Err(e) => {
+                if let Some(budget) = &self.budget {
+                    budget.release(bytes);
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Drop for ChunkedBumpAllocator {
+    fn drop(&mut self) {
+        if let Some(budget) = &self.budget {
+            budget.release(self.capacity());
+        }
+    }
+}
+
+/// Process-wide arena that is never cleared or freed, so its allocations can
+/// be handed out as `&'static` references. A pooled, cheap `Box::leak`.
+pub struct GlobalArena;
+
+struct GlobalArenaInner(ChunkedBumpAllocator);
+
+// SAFETY: The global arena only stores values through `alloc_static`, which
+// never registers drop closures, and all access goes through the mutex.
+unsafe impl Send for GlobalArenaInner {}
+
+impl GlobalArena {
+    const CHUNK_SIZE: usize = 4096;
+
+    fn inner() -> &'static Mutex<GlobalArenaInner> {
+        static ARENA: OnceLock<Mutex<GlobalArenaInner>> = OnceLock::new();
+        ARENA.get_or_init(|| {
+            let arena = ChunkedBumpAllocator::new(Self::CHUNK_SIZE)
+                .expect("GlobalArena::inner => Failed to create global arena");
+            Mutex::new(GlobalArenaInner(arena))
+        })
+    }
+
+    /// Moves `value` into the global arena and returns a reference valid for
+    /// the rest of the process. The value is never dropped.
+    pub fn alloc_static<T: Sync + 'static>(value: T) -> &'static T {
+        let mut arena = Self::inner().lock().unwrap_or_else(|e| e.into_inner());
+        let ptr = arena
+            .0
+            .alloc(ManuallyDrop::new(value))
+            .expect("GlobalArena::alloc_static => Out of memory");
+        // SAFETY: Chunks are never cleared, released or moved, so the
+        // allocation lives as long as the process.
+        unsafe { &*ptr.ptr }
+    }
+}
+
+/// Usage snapshot of a `DoubleBumpAllocator`, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStats {
+    /// Used by the active buffer (this frame).
+    pub current: usize,
+    /// Used by the inactive buffer (last frame).
+    pub previous: usize,
+    /// Highest usage either buffer has reached.
+    pub peak: usize,
+}
+
+pub struct DoubleBumpAllocator {
+    bufs: [BumpAllocator; 2],
+    current: usize,
+}
+
+impl DoubleBumpAllocator {
+    pub fn new(size_bytes: usize) -> anyhow::Result<Self> {
+        Self::with_align(size_bytes, BumpAllocator::DEFAULT_ALIGNMENT)
+    }
+
+    pub fn with_align(size_bytes: usize, align: usize) -> anyhow::Result<Self> {
+        let a = BumpAllocator::with_align(size_bytes, align)?;
+        let b = BumpAllocator::with_align(size_bytes, align)?;
+
+        let s = Self {
+            bufs: [a, b],
+            current: 0,
+        };
+        Ok(s)
+    }
+
+    pub fn swap(&mut self) {
+        self.current = self.inactive_index();
+    }
+
+    /// Allocates `data` into buffer `index` (0 or 1) regardless of which one
+    /// is active, e.g. to pre-populate the next frame.
+    pub fn alloc_in<T>(&mut self, index: usize, data: T) -> anyhow::Result<BumpPtr<T>> {
+        let Some(buf) = self.bufs.get_mut(index) else {
+            bail!("DoubleBumpAllocator::alloc_in => Buffer index must be 0 or 1");
+        };
+        buf.alloc(data)
+    }
+
+    /// Swaps and returns the buffer that was active until now, so it can be
+    /// read while the other one is filled.
+    pub fn swap_take(&mut self) -> &BumpAllocator {
+        self.swap();
+        self.previous()
+    }
+
+    /// Replaces both buffers with fresh ones of the same capacity aligned to
+    /// `align`. Contents are discarded, running any registered drops. If
+    /// either allocation fails, both old buffers are kept unchanged.
+    pub fn realign(&mut self, align: usize) -> anyhow::Result<()> {
+        if !align.is_power_of_two() {
+            bail!("DoubleBumpAllocator::realign => Alignment must be a power of two");
+        }
+        let a = BumpAllocator::with_align(self.bufs[0].capacity(), align)?;
+        let b = BumpAllocator::with_align(self.bufs[1].capacity(), align)?;
+        self.bufs = [a, b];
+        Ok(())
+    }
+
+    /// Index (0 or 1) of the buffer currently being allocated into.
+    pub fn active_index(&self) -> usize {
+        self.current
+    }
+
+    /// Index (0 or 1) of the buffer holding the previous frame's data.
+    pub fn inactive_index(&self) -> usize {
+        self.current ^ 1
+    }
+
+    pub fn current(&self) -> &BumpAllocator {
         &self.bufs[self.current]
     }
 
@@ -323,7 +4433,446 @@ impl DoubleBumpAllocator {
         &mut self.bufs[self.current]
     }
 
+    /// The inactive buffer, holding whatever was allocated before the last swap.
+    pub fn previous(&self) -> &BumpAllocator {
+        &self.bufs[self.inactive_index()]
+    }
+
+    pub fn frame_stats(&self) -> FrameStats {
+        FrameStats {
+            current: self.current().used(),
+            previous: self.previous().used(),
+            peak: self.bufs[0].peak().max(self.bufs[1].peak()),
+        }
+    }
+
+    /// Copies the inactive buffer's used region out, e.g. to archive last
+    /// frame's scratch data before it gets overwritten.
+    ///
+    /// # Safety
+    /// Same as `BumpAllocator::as_bytes`, for the inactive buffer.
+    pub unsafe fn take_previous_bytes(&self) -> Vec<u8> {
+        self.previous().as_bytes().to_vec()
+    }
+
     pub fn clear(&mut self) {
         self.current_mut().clear()
     }
 }
+
+/// One-line summary for log output, e.g.
+/// `DoubleBumpAllocator(active=0, current=120/4096 bytes, previous=88/4096 bytes)`.
+impl fmt::Display for DoubleBumpAllocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "DoubleBumpAllocator(active={}, current={}/{} bytes, previous={}/{} bytes)",
+            self.active_index(),
+            self.current().used(),
+            self.current().capacity(),
+            self.previous().used(),
+            self.previous().capacity()
+        )
+    }
+}
+
+/// Owns every per-frame arena of an engine and applies the right reset to
+/// each in one `end_frame` call, so none can be forgotten. `register_*`
+/// returns an index for reaching the arena again through the matching
+/// accessor.
+#[derive(Default)]
+pub struct FrameManager {
+    scratch: Vec<BumpAllocator>,
+    double: Vec<DoubleBumpAllocator>,
+    persistent: Vec<BumpAllocator>,
+    frame: u64,
+}
+
+impl FrameManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers an arena that is cleared at the end of every frame.
+    pub fn register_scratch(&mut self, arena: BumpAllocator) -> usize {
+        self.scratch.push(arena);
+        self.scratch.len() - 1
+    }
+
+    /// Registers a double buffer that is swapped at the end of every frame,
+    /// keeping the last frame's data readable through `previous`.
+    pub fn register_double(&mut self, arena: DoubleBumpAllocator) -> usize {
+        self.double.push(arena);
+        self.double.len() - 1
+    }
+
+    /// Registers an arena that `end_frame` never touches.
+    pub fn register_persistent(&mut self, arena: BumpAllocator) -> usize {
+        self.persistent.push(arena);
+        self.persistent.len() - 1
+    }
+
+    pub fn scratch(&mut self, index: usize) -> Option<&mut BumpAllocator> {
+        self.scratch.get_mut(index)
+    }
+
+    pub fn double(&mut self, index: usize) -> Option<&mut DoubleBumpAllocator> {
+        self.double.get_mut(index)
+    }
+
+    pub fn persistent(&mut self, index: usize) -> Option<&mut BumpAllocator> {
+        self.persistent.get_mut(index)
+    }
+
+    /// Number of frames ended so far.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Swaps every double buffer and clears its new current side, then
+    /// clears every scratch arena. Persistent arenas are left alone.
+    pub fn end_frame(&mut self) {
+        for double in &mut self.double {
+            double.swap();
+            double.clear();
+        }
+        for scratch in &mut self.scratch {
+            scratch.clear();
+        }
+        self.frame += 1;
+    }
+}
+
+/// A ring of `N` bump arenas, generalizing `DoubleBumpAllocator` to
+/// pipelines with more than one frame in flight. Data allocated `k` frames
+/// ago stays readable through `get(k)` until the ring comes back around.
+pub struct NBufferAllocator<const N: usize> {
+    bufs: [BumpAllocator; N],
+    current: usize,
+}
+
+impl<const N: usize> NBufferAllocator<N> {
+    pub fn new(size_bytes: usize) -> anyhow::Result<Self> {
+        Self::with_align(size_bytes, BumpAllocator::DEFAULT_ALIGNMENT)
+    }
+
+    pub fn with_align(size_bytes: usize, align: usize) -> anyhow::Result<Self> {
+        const { assert!(N > 0, "NBufferAllocator needs at least one buffer") };
+        let bufs = (0..N)
+            .map(|_| BumpAllocator::with_align(size_bytes, align))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let Ok(bufs) = bufs.try_into() else {
+            unreachable!("exactly N buffers were created");
+        };
+
+        let s = Self { bufs, current: 0 };
+        Ok(s)
+    }
+
+    /// Rotates to the next buffer in the ring and clears it, discarding the
+    /// data allocated `N - 1` frames ago.
+    pub fn advance(&mut self) {
+        self.current = (self.current + 1) % N;
+        self.current_mut().clear();
+    }
+
+    /// The buffer from `frames_back` frames ago; `get(0)` is the current one.
+    pub fn get(&self, frames_back: usize) -> Option<&BumpAllocator> {
+        (frames_back < N).then(|| &self.bufs[(self.current + N - frames_back) % N])
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.current
+    }
+
+    pub fn current(&self) -> &BumpAllocator {
+        &self.bufs[self.current]
+    }
+
+    pub fn current_mut(&mut self) -> &mut BumpAllocator {
+        &mut self.bufs[self.current]
+    }
+}
+
+/// Reference-counted `BumpAllocator` whose allocations keep it alive: each
+/// `SharedHandle` holds a clone of the `Rc`, so the arena is only freed once
+/// the last `SharedArena` and the last handle are gone. Clones refer to the
+/// same arena.
+#[derive(Clone)]
+pub struct SharedArena {
+    inner: Rc<SharedInner>,
+}
+
+struct SharedInner {
+    bump: RefCell<BumpAllocator>,
+    /// Live `SharedHandle`s, which `clear` refuses to invalidate.
+    handles: Cell<usize>,
+}
+
+impl SharedArena {
+    pub fn new(size_bytes: usize) -> anyhow::Result<Self> {
+        let inner = SharedInner {
+            bump: RefCell::new(BumpAllocator::new(size_bytes)?),
+            handles: Cell::new(0),
+        };
+        Ok(Self {
+            inner: Rc::new(inner),
+        })
+    }
+
+    pub fn alloc<T>(&self, data: T) -> anyhow::Result<SharedHandle<T>> {
+        let ptr = self.inner.bump.borrow_mut().alloc(data)?;
+        self.inner.handles.set(self.inner.handles.get() + 1);
+        Ok(SharedHandle {
+            arena: self.inner.clone(),
+            ptr: ptr.ptr,
+        })
+    }
+
+    pub fn used(&self) -> usize {
+        self.inner.bump.borrow().used()
+    }
+
+    /// Frees everything. Fails while any `SharedHandle` is still alive, since
+    /// it would be left dangling.
+    pub fn clear(&self) -> anyhow::Result<()> {
+        if self.inner.handles.get() > 0 {
+            bail!("SharedArena::clear => Cannot clear while handles are alive");
+        }
+        self.inner.bump.borrow_mut().clear();
+        Ok(())
+    }
+}
+
+/// Read-only handle to a value in a `SharedArena`, keeping the arena alive.
+pub struct SharedHandle<T> {
+    arena: Rc<SharedInner>,
+    ptr: *mut T,
+}
+
+impl<T> Deref for SharedHandle<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.ptr }
+    }
+}
+
+impl<T> Drop for SharedHandle<T> {
+    fn drop(&mut self) {
+        self.arena.handles.set(self.arena.handles.get() - 1);
+    }
+}
+
+/// Arena for servers that handle one request per arena. The arena can only
+/// be reached through the scope returned by `start`, which clears it first,
+/// so a request never sees, or forgets to free, the previous one's data.
+pub struct RequestArena {
+    bump: BumpAllocator,
+}
+
+impl RequestArena {
+    pub fn new(size_bytes: usize) -> anyhow::Result<Self> {
+        let s = Self {
+            bump: BumpAllocator::new(size_bytes)?,
+        };
+        Ok(s)
+    }
+
+    /// Clears the arena, starting a new epoch, and hands it out for the
+    /// duration of one request. `EpochOffset` handles stamped during earlier
+    /// requests no longer resolve.
+    pub fn start(&mut self) -> RequestScope<'_> {
+        self.bump.clear();
+        let epoch = self.bump.current_epoch();
+        RequestScope {
+            arena: &mut self.bump,
+            epoch,
+        }
+    }
+
+    /// Number of requests started so far.
+    pub fn requests(&self) -> u32 {
+        self.bump.current_epoch()
+    }
+}
+
+/// One request's view of a `RequestArena`; see `RequestArena::start`. In
+/// debug builds, dropping the scope panics if the arena was cleared
+/// mid-request, since anything allocated before that clear would have been
+/// left dangling.
+pub struct RequestScope<'a> {
+    arena: &'a mut BumpAllocator,
+    epoch: u32,
+}
+
+impl Deref for RequestScope<'_> {
+    type Target = BumpAllocator;
+
+    fn deref(&self) -> &Self::Target {
+        self.arena
+    }
+}
+
+impl DerefMut for RequestScope<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.arena
+    }
+}
+
+impl Drop for RequestScope<'_> {
+    fn drop(&mut self) {
+        debug_assert_eq!(
+            self.epoch,
+            self.arena.current_epoch(),
+            "RequestScope::drop => Arena was cleared mid-request"
+        );
+    }
+}
+
+/// Helper for recursive builders that arena-allocate as they descend. Each
+/// `nest` level records a marker and rewinds to it if the level fails, and
+/// allocations fail once the builder has used more than `max_used_bytes`, so
+/// runaway recursion ends in a clean error instead of filling the arena.
+pub struct ScopedBuilder<'a> {
+    arena: &'a mut BumpAllocator,
+    /// Arena marker when the builder was created.
+    base: usize,
+    /// Marker taken on entry to each open `nest` level.
+    markers: Vec<usize>,
+    max_used_bytes: usize,
+}
+
+impl<'a> ScopedBuilder<'a> {
+    pub fn new(arena: &'a mut BumpAllocator, max_used_bytes: usize) -> Self {
+        let base = arena.marker();
+        Self {
+            arena,
+            base,
+            markers: Vec::new(),
+            max_used_bytes,
+        }
+    }
+
+    /// Number of `nest` levels currently open.
+    pub fn depth(&self) -> usize {
+        self.markers.len()
+    }
+
+    /// Bytes allocated through this builder so far.
+    pub fn used_bytes(&self) -> usize {
+        self.arena.marker() - self.base
+    }
+
+    /// Allocates `data`, failing without allocating if that would take the
+    /// builder past `max_used_bytes`.
+    pub fn alloc<T>(&mut self, data: T) -> anyhow::Result<BumpPtr<T>> {
+        let marker = self.arena.marker();
+        let ptr = self.arena.alloc(data)?;
+        if self.used_bytes() > self.max_used_bytes {
+            self.arena.rewind(marker);
+            bail!(
+                "ScopedBuilder::alloc => Exceeded the {} byte limit at depth {}",
+                self.max_used_bytes,
+                self.depth()
+            );
+        }
+        Ok(ptr)
+    }
+
+    /// Runs `f` one level deeper. If it fails, everything allocated inside
+    /// the level is rewound before the error is returned.
+    pub fn nest<R>(&mut self, f: impl FnOnce(&mut Self) -> anyhow::Result<R>) -> anyhow::Result<R> {
+        let marker = self.arena.marker();
+        self.markers.push(marker);
+        let res = f(self);
+        self.markers.pop();
+        if res.is_err() {
+            self.arena.rewind(marker);
+        }
+        res
+    }
+}
+
+/// Interior-mutable wrapper that lets a `BumpAllocator` allocate through
+/// `&self`, which is what `GlobalAlloc` requires. The wrapper is neither
+/// `Send` nor `Sync`, so it never leaves its thread; the only misuse left is
+/// reentrant use, e.g. from a `Drop` run inside a failed allocation, which
+/// panics.
+pub struct BumpCell {
+    inner: UnsafeCell<BumpAllocator>,
+    borrowed: Cell<bool>,
+}
+
+impl BumpCell {
+    pub fn new(bump: BumpAllocator) -> Self {
+        Self {
+            inner: UnsafeCell::new(bump),
+            borrowed: Cell::new(false),
+        }
+    }
+
+    pub fn alloc<T>(&self, data: T) -> anyhow::Result<BumpPtr<T>> {
+        self.with(|bump| bump.alloc(data))
+    }
+
+    /// Like `BumpAllocator::alloc_stable`, but only borrows the cell shared,
+    /// so any number of `StableRef`s can be alive at once.
+    pub fn alloc_stable<T>(&self, data: T) -> anyhow::Result<StableRef<'_, T>> {
+        let ptr = self.alloc(data)?;
+        Ok(StableRef(unsafe { &*ptr.ptr }))
+    }
+
+    pub fn get_mut(&mut self) -> &mut BumpAllocator {
+        self.inner.get_mut()
+    }
+
+    pub fn into_inner(self) -> BumpAllocator {
+        self.inner.into_inner()
+    }
+
+    /// Whether an allocation is currently borrowing the inner arena; this is
+    /// the flag the reentrancy guard checks.
+    pub fn is_borrowed(&self) -> bool {
+        self.borrowed.get()
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&mut BumpAllocator) -> R) -> R {
+        self.try_with(f)
+            .expect("BumpCell => Reentrant allocation detected")
+    }
+
+    /// Like `with`, but returns `None` instead of panicking on reentrant use.
+    fn try_with<R>(&self, f: impl FnOnce(&mut BumpAllocator) -> R) -> Option<R> {
+        if self.borrowed.replace(true) {
+            return None;
+        }
+        let _guard = BorrowGuard(&self.borrowed);
+        Some(unsafe { f(&mut *self.inner.get()) })
+    }
+}
+
+struct BorrowGuard<'a>(&'a Cell<bool>);
+
+impl Drop for BorrowGuard<'_> {
+    fn drop(&mut self) {
+        self.0.set(false);
+    }
+}
+
+/// A `BumpCell` is `!Sync`, so it can't be installed as the
+/// `#[global_allocator]`; this impl is for code that takes any `GlobalAlloc`
+/// and calls it directly on one thread.
+unsafe impl GlobalAlloc for BumpCell {
+    /// Returns null rather than panicking on reentrant use, since a global
+    /// allocator must not unwind.
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.try_with(|bump| bump.bump(layout.size(), layout.align()).ok())
+            .flatten()
+            .unwrap_or(std::ptr::null_mut())
+    }
+
+    /// Individual allocations are never freed; memory is reclaimed when the
+    /// underlying arena is cleared or dropped.
+    unsafe fn dealloc(&self, _ptr: *mut u8, _layout: Layout) {}
+}
@@ -1,7 +1,18 @@
 #[cfg(test)]
 mod tests {
+    use std::{
+        alloc::{GlobalAlloc, System},
+        cell::{Cell, RefCell},
+        rc::Rc,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
 
-    use crate::{BumpAllocator, StackAllocator};
+    use crate::{
+        Backing, BumpAllocator, BumpCell, ChunkedBumpAllocator, ClearCost, ClearPolicy,
+        DoubleBumpAllocator, DownBumpAllocator, FreeListAllocator, GlobalArena, MemoryBudget,
+        NBufferAllocator, PoolAllocator, RelPtr, RequestArena, Schema, ScopedBuilder, SharedArena,
+        SoaPoolAllocator, StackAllocator, TypedBump,
+    };
 
     struct Point {
         x: f64,
@@ -24,7 +35,7 @@ mod tests {
             sa.clear();
         }
 
-        const S: &'static str = "aye lmao";
+        const S: &str = "aye lmao";
         let x = sa.alloc(String::from(S))?;
         assert_eq!(*x, S);
         Ok(())
@@ -47,10 +58,2795 @@ mod tests {
             ba.clear();
         }
 
-        const S: &'static str = "aye lmao";
+        const S: &str = "aye lmao";
         let x = ba.alloc(String::from(S))?;
         assert_eq!(*x, S);
 
         Ok(())
     }
+
+    #[test]
+    fn double_bump_indices() -> anyhow::Result<()> {
+        let mut dba = DoubleBumpAllocator::new(64)?;
+        assert_eq!(0, dba.active_index());
+        assert_eq!(1, dba.inactive_index());
+
+        for _ in 0..4 {
+            dba.swap();
+            let (a, i) = (dba.active_index(), dba.inactive_index());
+            assert!(a < 2 && i < 2);
+            assert_eq!(1, a ^ i);
+            dba.current_mut().alloc(a)?;
+        }
+        assert_eq!(0, dba.active_index());
+
+        Ok(())
+    }
+
+    #[test]
+    fn pool_monotonic() -> anyhow::Result<()> {
+        let mut pa = PoolAllocator::new_monotonic(3);
+
+        let a = pa.alloc(1)?;
+        let freed = &*a as *const i32;
+        pa.dealloc(a);
+
+        let b = pa.alloc(2)?;
+        assert_ne!(freed, &*b as *const i32);
+        assert_eq!(2, *b);
+
+        pa.alloc(3)?;
+        assert!(pa.alloc(4).is_err());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn rad_ptr_cast() -> anyhow::Result<()> {
+        let mut ba = BumpAllocator::with_align(64, align_of::<u32>())?;
+        let bytes = ba.alloc([1u8, 2, 3, 4])?;
+        let word = bytes.cast::<u32>();
+        assert_eq!(u32::from_ne_bytes([1, 2, 3, 4]), *word);
+
+        Ok(())
+    }
+
+    #[test]
+    fn pool_free_slots() -> anyhow::Result<()> {
+        let mut pa = PoolAllocator::new(4);
+        let a = pa.alloc(1)?;
+        let _b = pa.alloc(2)?;
+        pa.dealloc(a);
+
+        let free: Vec<isize> = pa.free_slots().collect();
+        assert_eq!(vec![0, 2, 3], free);
+
+        let c = pa.alloc(3)?;
+        assert_eq!(free[0], c.slot());
+
+        Ok(())
+    }
+
+    #[test]
+    fn bump_resettable() -> anyhow::Result<()> {
+        let mut ba = BumpAllocator::new(64)?;
+        ba.alloc(1u8)?;
+
+        let (scratch, token) = ba.alloc_resettable([0u8; 16])?;
+        assert_eq!(1, token);
+        assert_eq!([0u8; 16], *scratch);
+        ba.alloc(7u32)?;
+        assert!(ba.marker() > token + 16);
+
+        ba.rewind(token);
+        assert_eq!(token, ba.marker());
+        ba.alloc_resettable([0u8; 16])?;
+        assert_eq!(token + 16, ba.marker());
+
+        Ok(())
+    }
+
+    #[test]
+    fn bump_strided() -> anyhow::Result<()> {
+        let mut ba = BumpAllocator::new(256)?;
+        let a = ba.alloc_strided(1u32, 16)?;
+        let b = ba.alloc_strided(2u32, 16)?;
+        let c = ba.alloc_strided(3u32, 16)?;
+
+        let addr = |p: &crate::BumpPtr<u32>| &**p as *const u32 as usize;
+        assert_eq!(16, addr(&b) - addr(&a));
+        assert_eq!(16, addr(&c) - addr(&b));
+        assert_eq!((1, 2, 3), (*a, *b, *c));
+        assert!(ba.alloc_strided(4u32, 6).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn stack_boxed() -> anyhow::Result<()> {
+        const BIG: usize = 16 * 1024 * 1024;
+        let mut sa = StackAllocator::<BIG>::boxed();
+        assert_eq!(BIG, sa.len());
+        assert!(sa.is_empty());
+
+        let x = sa.alloc([7u8; 1024])?;
+        assert_eq!([7u8; 1024], *x);
+
+        Ok(())
+    }
+
+    #[test]
+    fn bump_transaction() -> anyhow::Result<()> {
+        let mut ba = BumpAllocator::new(32)?;
+        ba.alloc(1u8)?;
+        let before = ba.marker();
+
+        let res = ba.transaction(|ba| {
+            ba.alloc([0u8; 16])?;
+            ba.alloc([0u8; 64])?;
+            ba.alloc([0u8; 4])
+        });
+        assert!(res.is_err());
+        assert_eq!(before, ba.marker());
+
+        let ok = ba.transaction(|ba| ba.alloc(2u8))?;
+        assert_eq!(2, *ok);
+        assert_eq!(before + 1, ba.marker());
+
+        Ok(())
+    }
+
+    #[test]
+    fn bump_clone() -> anyhow::Result<()> {
+        let mut ba = BumpAllocator::with_align(64, 8)?;
+        ba.alloc(0xdead_beef_u32)?;
+        ba.alloc([1u8, 2, 3])?;
+
+        let mut cloned = ba.clone();
+        assert_eq!(unsafe { ba.as_bytes() }, unsafe { cloned.as_bytes() });
+        assert_eq!(ba.marker(), cloned.marker());
+
+        cloned.alloc(9u8)?;
+        assert_ne!(unsafe { ba.as_bytes() }, unsafe { cloned.as_bytes() });
+        assert_eq!(unsafe { ba.as_bytes() }, unsafe {
+            ba.try_clone()?.as_bytes()
+        });
+
+        ba.clear();
+        let x = ba.alloc(7u64)?;
+        let handle = ba.stamp(&x).expect("x is in the arena");
+        let copy = ba.try_clone()?;
+        assert_eq!((8, 8, 1), (copy.used(), copy.peak(), copy.current_epoch()));
+        assert_eq!(7, *unsafe { copy.resolve::<u64>(handle)? });
+
+        Ok(())
+    }
+
+    #[test]
+    fn chunked_budget() -> anyhow::Result<()> {
+        let budget = MemoryBudget::new(192);
+        let mut a = ChunkedBumpAllocator::with_budget(64, &budget)?;
+        let mut b = ChunkedBumpAllocator::with_budget(64, &budget)?;
+        assert_eq!(128, budget.used());
+
+        let first = a.alloc([1u8; 48])?;
+        a.alloc([2u8; 48])?;
+        assert_eq!(2, a.chunk_count());
+        assert_eq!([1u8; 48], *first);
+        assert_eq!(192, budget.used());
+
+        b.alloc([3u8; 48])?;
+        assert!(b.alloc([4u8; 48]).is_err());
+        assert_eq!(1, b.chunk_count());
+
+        a.clear();
+        assert_eq!(1, a.chunk_count());
+        assert_eq!(128, budget.used());
+
+        drop(a);
+        assert_eq!(64, budget.used());
+        b.alloc([4u8; 48])?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn bump_visit_schema() -> anyhow::Result<()> {
+        let mut ba = BumpAllocator::with_align(64, 4)?;
+        let mut schema = Schema::new();
+
+        schema.field::<u32>(ba.marker());
+        ba.alloc(0x0102_0304_u32)?;
+        schema.field::<[u8; 3]>(ba.marker());
+        ba.alloc([9u8, 8, 7])?;
+
+        let mut seen = Vec::new();
+        unsafe { ba.visit(&schema, |i, bytes| seen.push((i, bytes.to_vec()))) };
+        assert_eq!(
+            vec![
+                (0, 0x0102_0304_u32.to_ne_bytes().to_vec()),
+                (1, vec![9, 8, 7])
+            ],
+            seen
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn double_bump_previous_bytes() -> anyhow::Result<()> {
+        let mut dba = DoubleBumpAllocator::new(64)?;
+        dba.current_mut().alloc([1u8, 2, 3, 4])?;
+        dba.swap();
+        dba.clear();
+        dba.current_mut().alloc([5u8; 8])?;
+
+        assert_eq!(vec![1, 2, 3, 4], unsafe { dba.take_previous_bytes() });
+
+        Ok(())
+    }
+
+    #[test]
+    fn bump_cell_global_alloc() -> anyhow::Result<()> {
+        use std::alloc::{GlobalAlloc, Layout};
+
+        let cell = BumpCell::new(BumpAllocator::new(64)?);
+        let x = cell.alloc(5u32)?;
+        let raw = unsafe { GlobalAlloc::alloc(&cell, Layout::new::<u64>()) };
+        assert!(!raw.is_null());
+        assert_eq!(0, raw as usize % 8);
+        assert_eq!(5, *x);
+
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "Reentrant allocation detected")]
+    fn bump_cell_reentrant() {
+        struct Reenter<'a>(&'a BumpCell);
+        impl Drop for Reenter<'_> {
+            fn drop(&mut self) {
+                let _ = self.0.alloc(0u8);
+            }
+        }
+
+        let cell = BumpCell::new(BumpAllocator::new(4).unwrap());
+        // Too big to fit, so the value is dropped inside `alloc` and its Drop
+        // re-enters the cell.
+        let _ = cell.alloc((Reenter(&cell), [0u8; 16]));
+    }
+
+    #[test]
+    fn bump_dropper() -> anyhow::Result<()> {
+        use std::{cell::Cell, rc::Rc};
+
+        struct Counted(Rc<Cell<u32>>);
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let drops = Rc::new(Cell::new(0));
+        let mut ba = BumpAllocator::new(256)?;
+        let (_handle, dropper) = ba.alloc_with_dropper(Counted(drops.clone()))?;
+        ba.alloc_with_drop(Counted(drops.clone()))?;
+
+        dropper();
+        assert_eq!(1, drops.get());
+
+        ba.clear();
+        assert_eq!(2, drops.get());
+
+        Ok(())
+    }
+
+    #[test]
+    fn bump_collect_slice() -> anyhow::Result<()> {
+        let mut ba = BumpAllocator::new(64)?;
+        ba.alloc(1u8)?;
+
+        let slice = ba.collect_slice(0u32..10)?;
+        assert_eq!((0..10).collect::<Vec<u32>>(), slice);
+        slice[3] = 42;
+        assert_eq!(42, slice[3]);
+
+        let before = ba.marker();
+        assert!(ba.collect_slice(0u32..100).is_err());
+        assert_eq!(before, ba.marker());
+
+        Ok(())
+    }
+
+    #[test]
+    fn bump_offset_of() -> anyhow::Result<()> {
+        let mut ba = BumpAllocator::new(64)?;
+        ba.alloc(1u8)?;
+        let p = ba.alloc(0xabcd_u64)?;
+
+        let offset = ba.offset_of(&p).expect("pointer belongs to the arena");
+        let resolved = unsafe { ba.ptr_from_offset::<u64>(offset) }.expect("offset is valid");
+        assert_eq!(0xabcd, *resolved);
+
+        let other = BumpAllocator::new(64)?;
+        assert_eq!(None, other.offset_of(&p));
+        assert!(unsafe { ba.ptr_from_offset::<u64>(ba.marker()) }.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn pool_zeroing() -> anyhow::Result<()> {
+        let mut pa = PoolAllocator::new_zeroing(2);
+        let a = pa.alloc(0xdead_beef_u64)?;
+        let payload = &*a as *const u64;
+        pa.dealloc(a);
+        #[cfg(feature = "bytemuck")]
+        assert_eq!([0; 8], pa.as_bytes()[..8]);
+
+        let b = pa.alloc(7u64)?;
+        assert_eq!(payload, &*b as *const u64);
+        assert_eq!(7, *b);
+
+        Ok(())
+    }
+
+    #[test]
+    fn bump_with_remaining() -> anyhow::Result<()> {
+        let mut ba = BumpAllocator::new(64)?;
+        ba.alloc([1u8; 16])?;
+        let before = ba.marker();
+
+        let sum = ba.with_remaining(|scratch| {
+            assert_eq!(48, scratch.len());
+            scratch.iter_mut().for_each(|b| *b = 2);
+            scratch.iter().map(|&b| b as usize).sum::<usize>()
+        });
+        assert_eq!(96, sum);
+        assert_eq!(before, ba.marker());
+        assert_eq!(&[1u8; 16], unsafe { ba.as_bytes() });
+
+        Ok(())
+    }
+
+    #[test]
+    fn pool_ptr_identity() -> anyhow::Result<()> {
+        use std::collections::HashMap;
+
+        let mut pa = PoolAllocator::new(2);
+        let a = pa.alloc(10)?;
+        let stale = a.clone();
+        assert_eq!(a, stale);
+
+        let mut table = HashMap::new();
+        table.insert(a.clone(), "first");
+        pa.dealloc(a);
+
+        let b = pa.alloc(10)?;
+        assert_eq!(stale.slot(), b.slot());
+        assert_ne!(stale, b);
+        table.insert(b.clone(), "second");
+
+        assert_eq!(2, table.len());
+        assert_eq!(Some(&"first"), table.get(&stale));
+        assert_eq!(Some(&"second"), table.get(&b));
+
+        Ok(())
+    }
+
+    #[test]
+    fn stack_cache_aligned() -> anyhow::Result<()> {
+        let mut sa = StackAllocator::<512>::new();
+        sa.alloc(1u8)?;
+        let a = sa.alloc_cache_aligned(1u64)?;
+        let b = sa.alloc_cache_aligned(2u64)?;
+
+        let (a, b) = (&*a as *const u64 as usize, &*b as *const u64 as usize);
+        assert_eq!(0, a % 64);
+        assert_eq!(0, b % 64);
+        assert!(b - a >= 64);
+
+        Ok(())
+    }
+
+    #[test]
+    fn pool_reset_except() -> anyhow::Result<()> {
+        let mut pa = PoolAllocator::new(5);
+        let ptrs = (0..5)
+            .map(|i| pa.alloc(i))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let keep = [ptrs[1].clone(), ptrs[3].clone()];
+
+        pa.reset_except(&keep);
+        assert_eq!(1, *keep[0]);
+        assert_eq!(3, *keep[1]);
+        assert_eq!(vec![0, 2, 4], pa.free_slots().collect::<Vec<_>>());
+
+        for i in 10..13 {
+            pa.alloc(i)?;
+        }
+        assert!(pa.alloc(13).is_err());
+        assert_eq!(1, *keep[0]);
+        assert_eq!(3, *keep[1]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn bump_uniform_align() -> anyhow::Result<()> {
+        let mut ba = BumpAllocator::with_uniform_align(256, 16)?;
+        let addrs = [
+            &*ba.alloc(1u8)? as *const u8 as usize,
+            &*ba.alloc(2u64)? as *const u64 as usize,
+            &*ba.alloc([3u8; 5])? as *const [u8; 5] as usize,
+            &*ba.alloc(4u16)? as *const u16 as usize,
+        ];
+        assert!(addrs.iter().all(|a| a % 16 == 0));
+        assert_eq!(64, ba.marker());
+
+        let slice = ba.collect_slice(1u32..=4)?;
+        assert_eq!([1, 2, 3, 4], *slice);
+        assert_eq!(0, slice.as_ptr() as usize % 16);
+        assert_eq!(80, ba.marker());
+
+        Ok(())
+    }
+
+    /// Pushes its tag onto a shared log when dropped, for checking the order
+    /// destructors run in.
+    struct Logged<T: Copy>(T, Rc<RefCell<Vec<T>>>);
+
+    impl<T: Copy> Drop for Logged<T> {
+        fn drop(&mut self) {
+            self.1.borrow_mut().push(self.0);
+        }
+    }
+
+    /// Wraps the system allocator to count heap allocations per thread, so
+    /// tests can assert that a code path never touches the heap.
+    struct CountingAlloc;
+
+    thread_local! {
+        static HEAP_ALLOCS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAlloc {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            let _ = HEAP_ALLOCS.try_with(|n| n.set(n.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static COUNTING: CountingAlloc = CountingAlloc;
+
+    fn heap_allocs() -> usize {
+        HEAP_ALLOCS.with(|n| n.get())
+    }
+
+    #[test]
+    #[ignore = "benchmark: run with --ignored --release"]
+    fn bench_bump_alloc_no_heap() -> anyhow::Result<()> {
+        const N: usize = 1_000_000;
+        let mut ba = BumpAllocator::new(N * std::mem::size_of::<u64>())?;
+
+        let before = heap_allocs();
+        let start = std::time::Instant::now();
+        for i in 0..N {
+            std::hint::black_box(ba.alloc(i as u64)?);
+        }
+        let elapsed = start.elapsed();
+        assert_eq!(before, heap_allocs());
+        assert!(ba.try_alloc(0u64).is_err());
+        assert_eq!(before, heap_allocs());
+
+        println!("{N} allocations in {elapsed:?}");
+        Ok(())
+    }
+
+    #[test]
+    fn bump_slice_ptr() -> anyhow::Result<()> {
+        let mut ba = BumpAllocator::new(64)?;
+        ba.alloc(1u8)?;
+        let ptr = ba.alloc_slice_ptr(&[10u32, 20, 30])?;
+        assert_eq!(3, ptr.len());
+        assert_eq!(0, ptr.as_ptr() as *mut u32 as usize % align_of::<u32>());
+
+        let raw = ptr.as_ptr() as *const u32;
+        let rebuilt = unsafe { std::slice::from_raw_parts(raw, 3) };
+        assert_eq!(&[10, 20, 30], rebuilt);
+
+        let copy = ba.alloc_slice(b"hi")?;
+        assert_eq!(b"hi", &*copy);
+
+        Ok(())
+    }
+
+    #[test]
+    fn soa_pool_matches_aos() -> anyhow::Result<()> {
+        let mut aos = PoolAllocator::new(4);
+        let mut soa = SoaPoolAllocator::new(4);
+
+        let a: Vec<_> = (0..4)
+            .map(|i| aos.alloc(i))
+            .collect::<anyhow::Result<_>>()?;
+        let s: Vec<_> = (0..4)
+            .map(|i| soa.alloc(i))
+            .collect::<anyhow::Result<_>>()?;
+        assert!(aos.alloc(4).is_err() && soa.alloc(4).is_err());
+
+        aos.dealloc(a[1].clone());
+        soa.dealloc(s[1].clone());
+        aos.dealloc(a[2].clone());
+        soa.dealloc(s[2].clone());
+
+        let (x, y) = (aos.alloc(10)?, soa.alloc(10)?);
+        assert_eq!(x.slot(), y.slot());
+        assert_eq!(*x, *y);
+        assert_ne!(s[2].generation(), y.generation());
+
+        assert_eq!(
+            aos.iter().copied().collect::<Vec<_>>(),
+            soa.iter().copied().collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "benchmark: run with --ignored --release"]
+    fn bench_pool_iteration_density() -> anyhow::Result<()> {
+        const N: isize = 1 << 20;
+        let mut aos = PoolAllocator::new(N);
+        let mut soa = SoaPoolAllocator::new(N);
+        for i in 0..N as u64 {
+            aos.alloc(i)?;
+            soa.alloc(i)?;
+        }
+
+        let start = std::time::Instant::now();
+        let aos_sum: u64 = std::hint::black_box(aos.iter().sum());
+        let aos_time = start.elapsed();
+        let start = std::time::Instant::now();
+        let soa_sum: u64 = std::hint::black_box(soa.iter().sum());
+        let soa_time = start.elapsed();
+
+        assert_eq!(aos_sum, soa_sum);
+        println!("AoS iteration: {aos_time:?}, SoA iteration: {soa_time:?}");
+        Ok(())
+    }
+
+    #[test]
+    fn stack_shrink_bounds() -> anyhow::Result<()> {
+        let mut sa = StackAllocator::<64>::new();
+        sa.alloc([1u8; 8])?;
+
+        assert!(sa.shrink(9).is_err());
+        assert!(sa.shrink(1000).is_err());
+        assert!(sa.popn(9).is_err());
+
+        sa.popn(4)?;
+        sa.shrink(2)?;
+        let x = sa.alloc(5u8)?;
+        assert_eq!(5, *x);
+        sa.shrink(0)?;
+        assert!(sa.is_empty());
+
+        // Shrinking into the middle of a drop-tracked value drops it before
+        // its bytes are reused.
+        let log = Rc::new(RefCell::new(Vec::new()));
+        sa.alloc_with_drop(Logged(1u32, log.clone()))?;
+        sa.popn(4)?;
+        assert_eq!(*log.borrow(), [1]);
+        assert_eq!(0, sa.drop_entries().count());
+        sa.alloc(0x10u64)?;
+        sa.clear();
+        assert_eq!(*log.borrow(), [1]);
+
+        Ok(())
+    }
+
+    #[cfg(all(debug_assertions, feature = "debug-trace"))]
+    #[test]
+    #[should_panic(expected = "allocated as `u32`, read as `u64`")]
+    fn bump_read_checked_mismatch() {
+        let mut ba = BumpAllocator::with_align(64, 8).unwrap();
+        ba.track_types();
+        ba.alloc(7u32).unwrap();
+        ba.alloc(0u32).unwrap();
+
+        assert_eq!(7, unsafe { *ba.read_checked::<u32>(0) });
+        unsafe { ba.read_checked::<u64>(0) };
+    }
+
+    #[test]
+    fn bump_alloc_custom() -> anyhow::Result<()> {
+        let payload = b"hello";
+        let (layout, tail) = std::alloc::Layout::new::<u32>()
+            .extend(std::alloc::Layout::array::<u8>(payload.len())?)?;
+
+        let mut ba = BumpAllocator::new(64)?;
+        ba.alloc(1u8)?;
+        let ptr = ba.alloc_custom(layout, |raw| unsafe {
+            raw.cast::<u32>().write(payload.len() as u32);
+            std::ptr::copy_nonoverlapping(payload.as_ptr(), raw.add(tail), payload.len());
+        })?;
+
+        let (len, bytes) = unsafe {
+            let len = ptr.as_ptr().cast::<u32>().read() as usize;
+            (len, std::slice::from_raw_parts(ptr.as_ptr().add(tail), len))
+        };
+        assert_eq!(0, ptr.as_ptr() as usize % 4);
+        assert_eq!(5, len);
+        assert_eq!(payload, bytes);
+
+        Ok(())
+    }
+
+    #[test]
+    fn bump_coalesce_last_two() -> anyhow::Result<()> {
+        let mut ba = BumpAllocator::new(64)?;
+        let a = ba.alloc([1u8, 2, 3])?;
+        let b = ba.alloc([4u8, 5])?;
+        assert!(ba.last_two_contiguous(&a, &b));
+        assert!(!ba.last_two_contiguous(&b, &a));
+
+        let span = unsafe { ba.coalesce_last_two(&a, &b) }.expect("allocations are adjacent");
+        assert_eq!(&[1, 2, 3, 4, 5], span);
+        span[0] = 9;
+        assert_eq!([9, 2, 3], *a);
+
+        ba.alloc(0u8)?;
+        assert!(unsafe { ba.coalesce_last_two(&a, &b) }.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn bump_max_alloc() -> anyhow::Result<()> {
+        let mut ba = BumpAllocator::new(1024)?;
+        ba.set_max_alloc(16);
+
+        assert_eq!(
+            Err(crate::AllocError::ExceedsMaxAlloc),
+            ba.try_alloc([0u8; 17]).map(|_| ())
+        );
+        assert!(ba.alloc_slice(&[0u8; 32]).is_err());
+        assert!(ba
+            .alloc_layout(std::alloc::Layout::from_size_align(64, 1)?)
+            .is_err());
+        assert_eq!(0, ba.marker());
+
+        ba.alloc([0u8; 16])?;
+        ba.alloc_slice(&[1u8; 16])?;
+        assert_eq!(32, ba.marker());
+
+        Ok(())
+    }
+
+    #[test]
+    fn stack_drop_entries() -> anyhow::Result<()> {
+        use std::{cell::RefCell, rc::Rc};
+
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut sa = StackAllocator::<256>::new();
+        for i in 0..3 {
+            sa.alloc_with_drop(Logged(i, log.clone()))?;
+        }
+
+        let entries: Vec<_> = sa.drop_entries().collect();
+        assert_eq!(3, entries.len());
+        assert!(entries.windows(2).all(|w| w[0].0 < w[1].0));
+        assert!(entries
+            .iter()
+            .all(|&(_, size)| size == size_of::<Logged<u32>>()));
+
+        sa.clear();
+        assert_eq!(vec![2, 1, 0], *log.borrow());
+        assert_eq!(0, sa.drop_entries().count());
+
+        Ok(())
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn bump_needs_drop_hook() -> anyhow::Result<()> {
+        thread_local! {
+            static FLAGGED: std::cell::RefCell<Vec<&'static str>> = const {
+                std::cell::RefCell::new(Vec::new())
+            };
+        }
+        fn record(type_name: &'static str) {
+            FLAGGED.with(|f| f.borrow_mut().push(type_name));
+        }
+
+        let mut ba = BumpAllocator::new(256)?;
+        ba.set_needs_drop_hook(record);
+        ba.alloc(5u32)?;
+        ba.alloc_with_drop(String::from("tracked"))?;
+        assert!(FLAGGED.with(|f| f.borrow().is_empty()));
+
+        ba.alloc(String::from("leaked"))?;
+        assert_eq!(
+            vec![std::any::type_name::<String>()],
+            FLAGGED.with(|f| f.borrow().clone())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn pool_from_raw_parts() -> anyhow::Result<()> {
+        #[repr(align(16))]
+        struct Backing([u8; 512]);
+
+        let layout = PoolAllocator::<u64>::buffer_layout(8);
+        assert!(layout.size() <= 512 && layout.align() <= 16);
+
+        let mut backing = Backing([0; 512]);
+        let buf = std::ptr::NonNull::new(backing.0.as_mut_ptr()).unwrap();
+        let before = heap_allocs();
+        {
+            let mut pa = unsafe { PoolAllocator::<u64>::from_raw_parts(buf, 8) };
+            let a = pa.alloc(11)?;
+            let b = pa.alloc(22)?;
+            pa.dealloc(a);
+            assert_eq!(22, *b);
+            assert_eq!(11, pa.alloc(11).map(|p| *p)?);
+        }
+        assert_eq!(before, heap_allocs());
+
+        Ok(())
+    }
+
+    #[test]
+    fn double_bump_frame_stats() -> anyhow::Result<()> {
+        use crate::FrameStats;
+
+        let mut dba = DoubleBumpAllocator::new(256)?;
+        dba.current_mut().alloc([0u8; 100])?;
+        dba.swap();
+        dba.clear();
+        dba.current_mut().alloc([0u8; 40])?;
+        assert_eq!(
+            FrameStats {
+                current: 40,
+                previous: 100,
+                peak: 100
+            },
+            dba.frame_stats()
+        );
+
+        dba.swap();
+        dba.clear();
+        dba.current_mut().alloc([0u8; 10])?;
+        assert_eq!(
+            FrameStats {
+                current: 10,
+                previous: 40,
+                peak: 100
+            },
+            dba.frame_stats()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn rad_ptr_can_cast_to() -> anyhow::Result<()> {
+        let mut ba = BumpAllocator::with_align(64, 8)?;
+        let aligned = ba.alloc([0u8; 8])?;
+        let second = ba.alloc([0u8; 8])?;
+        ba.alloc(0u8)?;
+        let odd = ba.alloc([0u8; 8])?;
+
+        assert!(aligned.can_cast_to::<u64>());
+        assert!(aligned.can_cast_to::<u32>());
+        assert!(!aligned.can_cast_to::<u128>());
+        assert!(second.can_cast_to::<u64>());
+        assert!(!odd.can_cast_to::<u64>());
+        assert!(!odd.can_cast_to::<u16>());
+        assert!(odd.can_cast_to::<[u8; 4]>());
+
+        Ok(())
+    }
+
+    #[test]
+    fn bump_epoch_handles() -> anyhow::Result<()> {
+        let mut ba = BumpAllocator::new(64)?;
+        let p = ba.alloc(42u32)?;
+        let handle = ba.stamp(&p).expect("pointer belongs to the arena");
+        assert_eq!(42, *unsafe { ba.resolve::<u32>(handle) }?);
+
+        ba.clear();
+        ba.alloc(7u32)?;
+        assert_eq!(1, ba.current_epoch());
+        assert!(unsafe { ba.resolve::<u32>(handle) }.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn bump_dyn_slice() -> anyhow::Result<()> {
+        trait Handler {
+            fn handle(&mut self) -> u32;
+        }
+        struct Doubler(u32);
+        impl Handler for Doubler {
+            fn handle(&mut self) -> u32 {
+                self.0 *= 2;
+                self.0
+            }
+        }
+        struct Counter(u32);
+        impl Handler for Counter {
+            fn handle(&mut self) -> u32 {
+                self.0 += 1;
+                self.0
+            }
+        }
+
+        let mut doublers = BumpAllocator::new(256)?;
+        let mut counters = BumpAllocator::new(256)?;
+        let a = doublers.alloc_dyn_slice((1..=2).map(Doubler), |h| h as &mut dyn Handler)?;
+        let b = counters.alloc_dyn_slice((10..=11).map(Counter), |h| h as &mut dyn Handler)?;
+
+        let results: Vec<u32> = a
+            .iter_mut()
+            .chain(b.iter_mut())
+            .map(|h| h.handle())
+            .collect();
+        assert_eq!(vec![2, 4, 11, 12], results);
+        assert_eq!(2, a.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn bump_can_alloc() -> anyhow::Result<()> {
+        let mut ba = BumpAllocator::with_align(16, 8)?;
+        ba.alloc(1u8)?;
+
+        // 15 bytes are left, but a u64 needs 7 bytes of padding first.
+        assert_eq!(15, ba.remaining());
+        assert!(ba.can_alloc::<u64>());
+        assert!(!ba.can_alloc::<[u64; 2]>());
+        assert!(ba.can_alloc::<[u8; 15]>());
+
+        ba.alloc(2u64)?;
+        assert!(!ba.can_alloc::<u64>());
+        assert!(ba.alloc(3u64).is_err());
+
+        let mut sa = StackAllocator::<8>::new();
+        assert!(sa.can_alloc::<[u8; 8]>());
+        sa.alloc(1u8)?;
+        assert!(!sa.can_alloc::<[u8; 8]>());
+
+        Ok(())
+    }
+
+    #[test]
+    fn n_buffer_ring() -> anyhow::Result<()> {
+        let mut ring = NBufferAllocator::<3>::new(64)?;
+        for frame in 0..3u8 {
+            if frame > 0 {
+                ring.advance();
+            }
+            ring.current_mut().alloc(frame)?;
+        }
+
+        assert_eq!(&[2], unsafe { ring.get(0).unwrap().as_bytes() });
+        assert_eq!(&[1], unsafe { ring.get(1).unwrap().as_bytes() });
+        assert_eq!(&[0], unsafe { ring.get(2).unwrap().as_bytes() });
+        assert!(ring.get(3).is_none());
+
+        ring.advance();
+        assert!(ring.current().as_uninit_bytes().is_empty());
+        assert_eq!(&[1], unsafe { ring.get(2).unwrap().as_bytes() });
+        assert_eq!(0, ring.active_index());
+
+        Ok(())
+    }
+
+    #[test]
+    fn global_arena_static() {
+        let names: Vec<&'static str> = {
+            let owned: Vec<&'static String> = (0..3)
+                .map(|i| GlobalArena::alloc_static(format!("name{i}")))
+                .collect();
+            owned.into_iter().map(|s| s.as_str()).collect()
+        };
+        let n = GlobalArena::alloc_static(7u64);
+
+        assert_eq!(vec!["name0", "name1", "name2"], names);
+        assert_eq!(7, *n);
+        std::thread::spawn(move || assert_eq!("name1", names[1]))
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn stack_top_ptr() -> anyhow::Result<()> {
+        let mut stack = StackAllocator::<64>::new();
+        stack.alloc([1u8; 4])?;
+        let _ = stack.alloc([2u8; 2])?;
+
+        assert_eq!([2, 2], *stack.top_ptr::<[u8; 2]>().unwrap());
+        assert!(stack.top_ptr::<[u8; 4]>().is_none());
+
+        stack.popn(2)?;
+        assert_eq!([1; 4], *stack.top_ptr::<[u8; 4]>().unwrap());
+
+        stack.popn(1)?;
+        assert!(stack.top_ptr::<[u8; 4]>().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn pool_freeze_parallel_reads() -> anyhow::Result<()> {
+        let mut pool = PoolAllocator::<u64>::new(8);
+        for i in 0..6 {
+            pool.alloc(i)?;
+        }
+        let gone = pool.alloc(100)?;
+        pool.dealloc(gone);
+
+        let frozen = pool.freeze();
+        let sums: Vec<u64> = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| s.spawn(|| frozen.iter().sum::<u64>()))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        assert_eq!(vec![15; 4], sums);
+        assert_eq!(Some(&3), frozen.get(3));
+        assert_eq!(None, frozen.get(6));
+        assert_eq!(None, frozen.get(8));
+        Ok(())
+    }
+
+    #[test]
+    fn free_list_fragmentation() -> anyhow::Result<()> {
+        let mut heap = FreeListAllocator::new(64)?;
+        assert_eq!(0.0, heap.fragmentation());
+
+        let ptrs = (0..8u64)
+            .map(|i| heap.alloc(i))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        assert_eq!(0, heap.free_bytes());
+        assert_eq!(0.0, heap.fragmentation());
+
+        let (odd, even): (Vec<_>, Vec<_>) = ptrs.into_iter().partition(|p| **p % 2 == 1);
+        heap.dealloc(odd[0].clone())?;
+        assert_eq!(0.0, heap.fragmentation());
+        heap.dealloc(odd[1].clone())?;
+        assert_eq!(0.5, heap.fragmentation());
+        for p in &odd[2..] {
+            heap.dealloc(p.clone())?;
+        }
+        assert_eq!(0.75, heap.fragmentation());
+        assert!(heap.dealloc(odd[0].clone()).is_err());
+
+        for p in even {
+            heap.dealloc(p)?;
+        }
+        assert_eq!(64, heap.largest_free_block());
+        assert_eq!(0.0, heap.fragmentation());
+        Ok(())
+    }
+
+    #[test]
+    fn stable_ref_identity_keys() -> anyhow::Result<()> {
+        let cell = BumpCell::new(BumpAllocator::new(256)?);
+        let a = cell.alloc_stable(1u32)?;
+        let b = cell.alloc_stable(1u32)?;
+        assert_eq!(*a, *b);
+        assert_ne!(a, b);
+
+        let mut names = std::collections::HashMap::new();
+        names.insert(a, "a");
+        names.insert(b, "b");
+        assert_eq!(2, names.len());
+        assert_eq!(Some(&"a"), names.get(&a));
+        assert_eq!(Some(&"b"), names.get(&b));
+
+        let mut bump = BumpAllocator::new(64)?;
+        let c = bump.alloc_stable(5u8)?;
+        let copy = c;
+        assert_eq!(c, copy);
+        assert_eq!(5, *c);
+        Ok(())
+    }
+
+    #[test]
+    fn pool_reset_with() -> anyhow::Result<()> {
+        let mut pool = PoolAllocator::<String>::new(4);
+        let a = pool.alloc("a".to_string())?;
+        pool.alloc("b".to_string())?;
+        pool.alloc("c".to_string())?;
+        pool.dealloc(a);
+
+        let mut seen = Vec::new();
+        pool.reset_with(|s| seen.push(std::mem::take(s)));
+
+        assert_eq!(vec!["b", "c"], seen);
+        assert_eq!(0, pool.iter().count());
+        assert_eq!(vec![0, 1, 2, 3], pool.free_slots().collect::<Vec<_>>());
+        Ok(())
+    }
+
+    #[test]
+    fn bump_alloc_shared() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::new(64)?;
+        let shared = bump.alloc_shared([1u16, 2, 3])?;
+        let copies = [shared; 4];
+
+        let total: u16 = copies
+            .iter()
+            .chain([&shared])
+            .map(|c| c.iter().sum::<u16>())
+            .sum();
+        assert_eq!(30, total);
+        assert!(copies.iter().all(|c| std::ptr::eq(&**c, &*shared)));
+        Ok(())
+    }
+
+    #[test]
+    fn bump_clear_policy() -> anyhow::Result<()> {
+        for (policy, expected) in [
+            (ClearPolicy::Fast, [0xAB; 4]),
+            (ClearPolicy::Poison, [crate::POISON_BYTE; 4]),
+            (ClearPolicy::Zero, [0; 4]),
+        ] {
+            let mut bump = BumpAllocator::with_clear_policy(16, policy)?;
+            let ptr = bump.alloc([0xABu8; 4])?;
+            bump.clear();
+
+            let bytes = unsafe { std::ptr::read(ptr.ptr) };
+            assert_eq!(expected, bytes, "{policy:?}");
+            assert_eq!(0, bump.used());
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn pool_snapshot_restore() -> anyhow::Result<()> {
+        let mut pool = PoolAllocator::<u32>::new(4);
+        pool.alloc(10)?;
+        let b = pool.alloc(20)?;
+        pool.alloc(30)?;
+        pool.dealloc(b);
+
+        let snapshot = pool.snapshot();
+        assert_eq!(2, snapshot.header.live);
+        let free = pool.free_slots().collect::<Vec<_>>();
+
+        pool.reset_except(&[]);
+        pool.alloc(99)?;
+        unsafe { pool.restore(&snapshot)? };
+
+        assert_eq!(vec![&10, &30], pool.iter().collect::<Vec<_>>());
+        assert_eq!(free, pool.free_slots().collect::<Vec<_>>());
+        assert_eq!(snapshot.header, pool.header());
+        assert_eq!(snapshot.bytes, pool.as_bytes());
+        assert!(unsafe { PoolAllocator::<u32>::new(2).restore(&snapshot) }.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn bump_sub_aligned() -> anyhow::Result<()> {
+        let mut parent = BumpAllocator::new(256)?;
+        parent.alloc(1u8)?;
+        assert_eq!(1, parent.used());
+
+        {
+            let mut child = parent.sub_aligned(64, 32)?;
+            assert_eq!(64, child.capacity());
+            let first = child.alloc(7u8)?;
+            assert_eq!(0, first.ptr as usize % 32);
+            child.alloc_with_drop(String::from("child"))?;
+        }
+        assert!(parent.used() >= 65);
+
+        let mut child = parent.sub(8)?;
+        child.alloc(1u64)?;
+        assert!(child.alloc(1u8).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn rad_ptr_aliases() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::new(64)?;
+        let whole = bump.alloc([0u32; 4])?;
+        let after = bump.alloc(0u32)?;
+
+        let inner = crate::RadPtr {
+            ptr: whole.ptr.cast::<u32>().wrapping_add(1),
+        };
+        assert!(whole.aliases(&inner));
+        assert!(inner.aliases(&whole));
+        assert!(whole.aliases(&whole));
+
+        assert!(!whole.aliases(&after));
+        assert!(!after.aliases(&whole));
+        Ok(())
+    }
+
+    #[test]
+    fn free_list_min_block() -> anyhow::Result<()> {
+        let mut heap = FreeListAllocator::with_min_block(64, 16)?;
+        let ptrs = (0..4u8)
+            .map(|i| heap.alloc(i))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        assert_eq!(0, heap.free_bytes());
+        for pair in ptrs.windows(2) {
+            assert_eq!(16, pair[1].ptr as usize - pair[0].ptr as usize);
+        }
+        assert!(heap.alloc(0u8).is_err());
+
+        heap.dealloc(ptrs[0].clone())?;
+        heap.dealloc(ptrs[2].clone())?;
+        assert_eq!(0.5, heap.fragmentation());
+        heap.dealloc(ptrs[1].clone())?;
+        heap.dealloc(ptrs[3].clone())?;
+        assert_eq!(64, heap.largest_free_block());
+        assert_eq!(0.0, heap.fragmentation());
+        Ok(())
+    }
+
+    #[test]
+    fn pool_migrate_into() -> anyhow::Result<()> {
+        let mut small = PoolAllocator::<String>::new(4);
+        let a = small.alloc("a".to_string())?;
+        small.alloc("b".to_string())?;
+        small.alloc("c".to_string())?;
+        small.dealloc(a);
+
+        let mut big = PoolAllocator::<String>::new(8);
+        big.alloc("x".to_string())?;
+        let remap = small.migrate_into(&mut big);
+
+        assert_eq!(std::collections::HashMap::from([(1, 1), (2, 2)]), remap);
+        assert_eq!(0, small.iter().count());
+        assert_eq!(4, small.free_slots().count());
+        let values: Vec<_> = big.iter().cloned().collect();
+        assert_eq!(vec!["x", "b", "c"], values);
+        Ok(())
+    }
+
+    #[test]
+    fn bump_alloc_uninit_bytes() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::new(64)?;
+        let buf = bump.alloc_uninit_bytes(6)?;
+        let (head, tail) = buf.split_at_mut(2);
+        for (dst, src) in head.iter_mut().zip(b"ze") {
+            dst.write(*src);
+        }
+        for (dst, src) in tail.iter_mut().zip(b"n_ok") {
+            dst.write(*src);
+        }
+
+        let bytes = unsafe { crate::assume_init_bytes(buf) };
+        assert_eq!(b"zen_ok", bytes);
+        assert_eq!(6, bump.used());
+        Ok(())
+    }
+
+    #[test]
+    fn bump_allocation_stats() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::new(128)?;
+        assert_eq!(0.0, bump.average_alloc_size());
+
+        bump.alloc(1u64)?;
+        bump.alloc(2u32)?;
+        bump.alloc([0u8; 4])?;
+        bump.alloc_slice(&[0u16; 8])?;
+
+        assert_eq!(4, bump.allocation_count());
+        assert_eq!(32, bump.used());
+        assert_eq!(8.0, bump.average_alloc_size());
+
+        bump.collect_slice(0u32..3)?;
+        assert_eq!(5, bump.allocation_count());
+
+        bump.clear();
+        assert_eq!(0, bump.allocation_count());
+        Ok(())
+    }
+
+    #[test]
+    fn double_bump_realign() -> anyhow::Result<()> {
+        let mut bufs = DoubleBumpAllocator::with_align(256, 8)?;
+        bufs.current_mut().alloc(1u8)?;
+        assert!(bufs.realign(48).is_err());
+        assert_eq!(1, bufs.current().used());
+
+        bufs.realign(64)?;
+        assert_eq!(0, bufs.current().used());
+        for _ in 0..2 {
+            let ptr = bufs
+                .current_mut()
+                .alloc_layout(std::alloc::Layout::from_size_align(1, 64)?)?;
+            assert_eq!(0, ptr.as_ptr() as usize % 64);
+            bufs.swap();
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test]
+    fn bump_fail_next_allocs() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::new(64)?;
+        bump.fail_next_allocs(2);
+
+        assert!(bump.alloc(1u8).is_err());
+        assert_eq!(
+            Err(crate::AllocError::OutOfMemory),
+            bump.try_alloc(2u8).map(|_| ())
+        );
+        assert_eq!(3, *bump.alloc(3u8)?);
+        assert_eq!(1, bump.used());
+        Ok(())
+    }
+
+    #[test]
+    fn bump_alloc_gen() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::new(64)?;
+        let (ptr, epoch) = bump.alloc_gen(5u32)?;
+        assert_eq!(epoch, bump.current_epoch());
+        assert_eq!(5, *ptr);
+
+        bump.clear();
+        assert_ne!(epoch, bump.current_epoch());
+        let (_, next) = bump.alloc_gen(6u32)?;
+        assert_eq!(epoch + 1, next);
+        Ok(())
+    }
+
+    #[cfg(all(feature = "mmap", target_os = "linux"))]
+    #[test]
+    fn bump_mremap_growth() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::new_mremap(4096, 1 << 20)?;
+        assert_eq!(4096, bump.capacity());
+        let first = bump.alloc(0xC0FFEEu64)?;
+        let base = bump.as_uninit_bytes().as_ptr();
+
+        let big = bump.alloc_slice(&[7u8; 10_000])?;
+        assert_eq!(10_000, big.len());
+        assert!(bump.capacity() >= 10_008);
+        assert_eq!(base, bump.as_uninit_bytes().as_ptr());
+        assert_eq!(0xC0FFEE, *first);
+
+        assert!(bump.alloc_slice(&vec![0u8; 1 << 20]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn bump_next_padding() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::with_align(64, 8)?;
+        assert_eq!(0, bump.next_padding::<u64>());
+
+        bump.alloc(1u8)?;
+        assert_eq!(7, bump.next_padding::<u64>());
+        assert_eq!(1, bump.next_padding::<u16>());
+        assert_eq!(0, bump.next_padding::<u8>());
+
+        bump.alloc(2u64)?;
+        assert_eq!(16, bump.used());
+        Ok(())
+    }
+
+    #[test]
+    fn drop_order_is_reverse_allocation() -> anyhow::Result<()> {
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        let mut bump = BumpAllocator::new(256)?;
+        for i in 0..4 {
+            bump.alloc_with_drop(Logged(i, log.clone()))?;
+        }
+        bump.clear();
+        assert_eq!(vec![3, 2, 1, 0], log.take());
+
+        for i in 0..3 {
+            bump.alloc_with_drop(Logged(i, log.clone()))?;
+        }
+        drop(bump);
+        assert_eq!(vec![2, 1, 0], log.take());
+
+        let mut stack = StackAllocator::<256>::new();
+        for i in 0..4 {
+            stack.alloc_with_drop(Logged(i, log.clone()))?;
+        }
+        stack.clear();
+        assert_eq!(vec![3, 2, 1, 0], log.take());
+
+        for i in 0..3 {
+            stack.alloc_with_drop(Logged(i, log.clone()))?;
+        }
+        drop(stack);
+        assert_eq!(vec![2, 1, 0], log.take());
+        Ok(())
+    }
+
+    #[test]
+    fn bump_alloc_vec() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::new(128)?;
+        let mut v = bump.alloc_vec::<u32>(3)?;
+        assert!(v.is_empty());
+        for i in 1..=3 {
+            v.push(i * 10)?;
+        }
+        assert!(v.push(40).is_err());
+
+        v[1] += 1;
+        assert_eq!(&[10, 21, 30], v.as_slice());
+        assert_eq!(3, v.capacity());
+        drop(v);
+        assert_eq!(12, bump.used());
+        Ok(())
+    }
+
+    #[test]
+    fn double_bump_swap_take() -> anyhow::Result<()> {
+        let mut bufs = DoubleBumpAllocator::new(64)?;
+        bufs.current_mut().alloc_slice(&[1u8, 2, 3])?;
+
+        let filled = bufs.swap_take();
+        assert_eq!(&[1, 2, 3], unsafe { filled.as_bytes() });
+        assert_eq!(1, bufs.active_index());
+        assert!(bufs.current().as_uninit_bytes().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn typed_bump_index() -> anyhow::Result<()> {
+        let mut arena = TypedBump::<String>::new(4)?;
+        for name in ["a", "b", "c"] {
+            arena.alloc(name.to_string())?;
+        }
+        assert_eq!(3, arena.len());
+        assert_eq!(4, arena.capacity());
+
+        arena[1].push('!');
+        assert_eq!("b!", arena[1]);
+        assert_eq!("c", arena[2]);
+
+        let out_of_range =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| arena[3].len()));
+        assert!(out_of_range.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn typed_bump_clear_panicking_drop() -> anyhow::Result<()> {
+        struct Bomb(Rc<Cell<usize>>);
+        impl Drop for Bomb {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+                if self.0.get() == 1 {
+                    panic!("first drop");
+                }
+            }
+        }
+
+        let drops = Rc::new(Cell::new(0));
+        let mut arena = TypedBump::new(4)?;
+        arena.alloc(Bomb(drops.clone()))?;
+        arena.alloc(Bomb(drops.clone()))?;
+
+        let cleared = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| arena.clear()));
+        assert!(cleared.is_err());
+        assert_eq!(2, drops.get());
+        assert!(arena.is_empty());
+
+        arena.alloc(Bomb(drops.clone()))?;
+        drop(arena);
+        assert_eq!(3, drops.get());
+        Ok(())
+    }
+
+    #[test]
+    fn bump_alloc_borrowing() -> anyhow::Result<()> {
+        struct Pair<'src> {
+            key: &'src str,
+            value: &'src str,
+        }
+
+        let input = String::from("name=zen");
+        let mut bump = BumpAllocator::new(64)?;
+        let (key, value) = input.split_once('=').unwrap();
+        let pair = bump.alloc_borrowing(Pair { key, value })?;
+        pair.value = &input[5..];
+
+        assert_eq!("name", pair.key);
+        assert_eq!("zen", pair.value);
+        assert!(std::ptr::eq(input.as_ptr(), pair.key.as_ptr()));
+        Ok(())
+    }
+
+    #[test]
+    fn allocator_backing() -> anyhow::Result<()> {
+        assert_eq!(Backing::InlineStack, StackAllocator::<16>::new().backing());
+        assert_eq!(Backing::Heap, PoolAllocator::<u32>::new(2).backing());
+        assert_eq!(Backing::Heap, SoaPoolAllocator::<u32>::new(2).backing());
+        assert_eq!(Backing::Heap, FreeListAllocator::new(16)?.backing());
+        assert_eq!(Backing::Heap, ChunkedBumpAllocator::new(16)?.backing());
+        assert_eq!(Backing::Heap, TypedBump::<u32>::new(2)?.backing());
+
+        let mut bump = BumpAllocator::new(256)?;
+        assert_eq!(Backing::Heap, bump.backing());
+        assert_eq!(Backing::BorrowedSlice, bump.sub(16)?.backing());
+
+        let layout = PoolAllocator::<u32>::buffer_layout(2);
+        let buf = bump.alloc_layout(layout)?;
+        let pool = unsafe { PoolAllocator::<u32>::from_raw_parts(buf, 2) };
+        assert_eq!(Backing::BorrowedSlice, pool.backing());
+
+        #[cfg(all(feature = "mmap", target_os = "linux"))]
+        assert_eq!(
+            Backing::Mmap,
+            BumpAllocator::new_mremap(4096, 8192)?.backing()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn pool_fold() -> anyhow::Result<()> {
+        let mut pool = PoolAllocator::<u32>::new(5);
+        let ptrs = (1..=5)
+            .map(|v| pool.alloc(v))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        pool.dealloc(ptrs[1].clone());
+        pool.dealloc(ptrs[3].clone());
+
+        assert_eq!(9, pool.fold(0, |acc, v| acc + v));
+        assert_eq!(9, pool.sum());
+        assert_eq!(
+            vec![1, 3, 5],
+            pool.fold(Vec::new(), |mut acc, &v| {
+                acc.push(v);
+                acc
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn double_bump_alloc_in() -> anyhow::Result<()> {
+        let mut bufs = DoubleBumpAllocator::new(64)?;
+        let next = bufs.inactive_index();
+        let ptr = bufs.alloc_in(next, 42u8)?;
+        assert!(bufs.current().as_uninit_bytes().is_empty());
+        assert!(bufs.alloc_in(2, 0u8).is_err());
+
+        bufs.swap();
+        assert_eq!(&[42], unsafe { bufs.current().as_bytes() });
+        assert_eq!(42, *ptr);
+        Ok(())
+    }
+
+    #[test]
+    fn bump_fill_from() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::with_align(32, 4)?;
+        let mut items = 0..100u32;
+
+        assert_eq!(8, bump.fill_from(&mut items));
+        assert_eq!(32, bump.used());
+        assert_eq!(Some(8), items.next());
+        assert_eq!(91, items.count());
+
+        let mut padded = BumpAllocator::with_align(128, 8)?;
+        padded.set_max_padding(0);
+        padded.alloc(1u8)?;
+        let mut wide = [1u64, 2, 3].into_iter();
+        assert_eq!(0, padded.fill_from(&mut wide));
+        assert_eq!(3, wide.count());
+        Ok(())
+    }
+
+    #[test]
+    fn bump_content_hash() -> anyhow::Result<()> {
+        let build = |last: u8| -> anyhow::Result<BumpAllocator> {
+            let mut bump = BumpAllocator::new(64)?;
+            bump.alloc(0x1234_5678u32)?;
+            bump.alloc_slice(&[1u8, 2, last])?;
+            Ok(bump)
+        };
+        let (a, b, c) = (build(3)?, build(3)?, build(4)?);
+
+        assert_eq!(unsafe { a.content_hash() }, unsafe { b.content_hash() });
+        assert_ne!(unsafe { a.content_hash() }, unsafe { c.content_hash() });
+        assert_eq!(0xcbf2_9ce4_8422_2325, unsafe {
+            BumpAllocator::new(8)?.content_hash()
+        });
+        Ok(())
+    }
+
+    #[test]
+    fn pool_payload_alignment() -> anyhow::Result<()> {
+        #[repr(align(32))]
+        struct Simd([f32; 8]);
+
+        let mut pool = PoolAllocator::<Simd>::new(5);
+        let ptrs = (0..5)
+            .map(|i| pool.alloc(Simd([i as f32; 8])))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        for (i, ptr) in ptrs.iter().enumerate() {
+            let value: &Simd = ptr;
+            assert_eq!(0, value as *const Simd as usize % 32);
+            assert_eq!(i as f32, value.0[7]);
+        }
+        assert!(pool
+            .iter()
+            .all(|v| (v as *const Simd as usize).is_multiple_of(32)));
+        Ok(())
+    }
+
+    #[test]
+    fn bump_untracked_scope() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::new(256)?;
+        bump.alloc_with_drop(String::from("tracked"))?;
+        assert_eq!(1, bump.tracked_drops());
+
+        let sum = bump.untracked_scope(|bump| -> anyhow::Result<u32> {
+            let a = bump.alloc_with_drop(1u32)?;
+            let b = bump.alloc_with_drop([2u32; 4])?;
+            Ok(*a + b.iter().sum::<u32>())
+        })?;
+        assert_eq!(9, sum);
+        assert_eq!(1, bump.tracked_drops());
+
+        bump.alloc_with_drop(String::from("tracked again"))?;
+        assert_eq!(2, bump.tracked_drops());
+        Ok(())
+    }
+
+    #[cfg(feature = "debug-trace")]
+    #[test]
+    fn bump_same_layout() -> anyhow::Result<()> {
+        let build = |swap: bool| -> anyhow::Result<BumpAllocator> {
+            let mut bump = BumpAllocator::with_align(64, 8)?;
+            if swap {
+                bump.alloc(2u64)?;
+                bump.alloc(1u8)?;
+            } else {
+                bump.alloc(1u8)?;
+                bump.alloc(2u64)?;
+            }
+            Ok(bump)
+        };
+        let (a, b, c) = (build(false)?, build(false)?, build(true)?);
+
+        assert!(a.same_layout(&b));
+        assert!(!a.same_layout(&c));
+        Ok(())
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn soa_pool_contiguous_live_range() -> anyhow::Result<()> {
+        let mut pool = SoaPoolAllocator::<[u8; 4]>::new(4);
+        pool.alloc([1; 4])?;
+        pool.alloc([2; 4])?;
+        pool.alloc([3; 4])?;
+
+        let bytes = pool.contiguous_live_range(1, 2).unwrap();
+        assert_eq!(&[2, 2, 2, 2, 3, 3, 3, 3], bytes);
+        assert_eq!(12, pool.contiguous_live_range(0, 3).unwrap().len());
+        assert!(pool.contiguous_live_range(2, 2).is_none());
+        assert!(pool.contiguous_live_range(3, 5).is_none());
+        assert!(pool.contiguous_live_range(-1, 1).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn pool_round_robin() -> anyhow::Result<()> {
+        let mut pool = PoolAllocator::<u32>::new_round_robin(4);
+        let mut slots = Vec::new();
+        for i in 0..6 {
+            let ptr = pool.alloc(i)?;
+            slots.push(ptr.slot());
+            pool.dealloc(ptr);
+        }
+        assert_eq!(vec![0, 1, 2, 3, 0, 1], slots);
+
+        let held = pool.alloc(10)?;
+        assert_eq!(2, held.slot());
+        assert_eq!(vec![3, 0, 1], pool.free_slots().collect::<Vec<_>>());
+        pool.check_invariants()?;
+        let slots = (0..3)
+            .map(|i| pool.alloc(i).map(|p| p.slot()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        assert_eq!(vec![3, 0, 1], slots);
+        assert!(pool.alloc(0).is_err());
+        assert_eq!(0, pool.free_slots().count());
+        Ok(())
+    }
+
+    #[test]
+    fn bump_alloc_builder() -> anyhow::Result<()> {
+        struct Big {
+            id: u32,
+            weights: [f32; 64],
+            name: &'static str,
+        }
+
+        let mut bump = BumpAllocator::new(512)?;
+        let mut builder = bump.alloc_builder::<Big>()?;
+        let p = builder.as_mut_ptr();
+        let big = unsafe {
+            std::ptr::addr_of_mut!((*p).id).write(7);
+            std::ptr::addr_of_mut!((*p).weights).write([0.5; 64]);
+            std::ptr::addr_of_mut!((*p).name).write("big");
+            builder.finish()
+        };
+
+        assert_eq!(7, big.id);
+        assert_eq!(32.0, big.weights.iter().sum::<f32>());
+        assert_eq!("big", big.name);
+        Ok(())
+    }
+
+    #[test]
+    fn bump_writer() -> anyhow::Result<()> {
+        use std::io::Write;
+
+        let mut bump = BumpAllocator::new(64)?;
+        let mut w = bump.writer(10)?;
+        w.write_all(&7u32.to_le_bytes())?;
+        w.write_all(b"zen")?;
+        write!(w, "{}", 42)?;
+        assert_eq!(1, w.remaining());
+        assert!(w.write_all(b"ab").is_err());
+
+        let bytes = w.finish();
+        assert_eq!(b"\x07\0\0\0zen42a", bytes);
+        assert_eq!(10, bump.used());
+        Ok(())
+    }
+
+    #[test]
+    fn bump_transaction_rollback_drops() -> anyhow::Result<()> {
+        thread_local! {
+            static ZST_DROPS: Cell<usize> = const { Cell::new(0) };
+        }
+        struct Zst;
+        impl Drop for Zst {
+            fn drop(&mut self) {
+                ZST_DROPS.with(|c| c.set(c.get() + 1));
+            }
+        }
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        let mut bump = BumpAllocator::new(256)?;
+        bump.alloc_with_drop(Logged("before", log.clone()))?;
+        bump.alloc_with_drop(Zst)?;
+        let res = bump.transaction(|bump| -> anyhow::Result<()> {
+            bump.alloc_with_drop(Logged("first", log.clone()))?;
+            bump.alloc_with_drop(Logged("second", log.clone()))?;
+            anyhow::bail!("parse failed")
+        });
+        assert!(res.is_err());
+        assert_eq!(vec!["second", "first"], log.take());
+        assert_eq!(0, ZST_DROPS.with(Cell::get));
+        assert_eq!(2, bump.tracked_drops());
+
+        drop(bump);
+        assert_eq!(vec!["before"], log.take());
+        assert_eq!(1, ZST_DROPS.with(Cell::get));
+        Ok(())
+    }
+
+    #[test]
+    fn down_bump_alloc_aligned() -> anyhow::Result<()> {
+        let mut bump = DownBumpAllocator::with_align(256, 64)?;
+        let a = bump.alloc(1u8)?;
+        assert_eq!(1, bump.used());
+
+        let before = a.ptr as usize;
+        let b = bump.alloc_aligned([7u8; 3], 64)?;
+        let addr = b.ptr as usize;
+        assert!(addr.is_multiple_of(64));
+        assert!(addr + 3 <= before);
+        assert_eq!(64, bump.used());
+        assert_eq!(1, *a);
+        assert_eq!([7; 3], *b);
+
+        assert!(bump.alloc([0u8; 193]).is_err());
+        assert!(bump.alloc([0u8; 192]).is_ok());
+        assert_eq!(0, bump.remaining());
+        Ok(())
+    }
+
+    #[test]
+    fn alloc_macro() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::new(64)?;
+        crate::alloc!(bump, a = 1u8, b = 2u32, c = [3u16; 2]);
+        assert_eq!(1, *a);
+        assert_eq!(2, *b);
+        assert_eq!([3, 3], *c);
+
+        let mut pool = PoolAllocator::<u32>::new(2);
+        crate::alloc!(pool, x = 10, y = 20,);
+        assert_eq!(30, *x + *y);
+        Ok(())
+    }
+
+    #[test]
+    fn bump_capacity_for() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::with_align(64, 8)?;
+        bump.alloc(1u8)?;
+        let (min, max) = (
+            bump.min_capacity_for::<u64>(),
+            bump.max_capacity_for::<u64>(),
+        );
+        assert_eq!((7, 7), (min, max));
+
+        let mut fitted = 0;
+        while bump.alloc(0u64).is_ok() {
+            fitted += 1;
+        }
+        assert!(min <= fitted && fitted <= max);
+        assert_eq!(usize::MAX, bump.min_capacity_for::<()>());
+        Ok(())
+    }
+
+    #[cfg(feature = "debug-trace")]
+    #[test]
+    fn bump_memory_map() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::with_align(64, 8)?;
+        bump.alloc_tagged(1u8, "audio")?;
+        bump.alloc(0u16)?;
+        let marker = bump.marker();
+        bump.alloc_tagged([0u32; 3], "physics")?;
+        assert_eq!(vec![(0, 1, "audio"), (4, 12, "physics")], bump.memory_map());
+
+        bump.rewind(marker);
+        assert_eq!(vec![(0, 1, "audio")], bump.memory_map());
+        Ok(())
+    }
+
+    #[test]
+    fn bump_reserve_region() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::new(256)?;
+        bump.alloc(1u32)?;
+
+        let mut region = bump.reserve_region(100)?;
+        region.write(&[1; 10])?;
+        region.write(&[2; 20])?;
+        assert!(region.write(&[0; 71]).is_err());
+        let bytes = region.finish();
+        assert_eq!(30, bytes.len());
+        assert_eq!([1, 2], [bytes[9], bytes[10]]);
+
+        assert_eq!(34, bump.used());
+        assert_eq!(256 - 34, bump.remaining());
+
+        // Uniform alignment rounds the reservation up past `max`.
+        let mut uniform = BumpAllocator::with_uniform_align(256, 8)?;
+        let mut region = uniform.reserve_region(10)?;
+        region.write(&[3; 4])?;
+        assert_eq!([3; 4], region.finish());
+        assert_eq!(4, uniform.used());
+
+        // A zero-sized drop-tracked value shares the region's start offset.
+        let mut zst = BumpAllocator::new(64)?;
+        zst.alloc_with_drop(())?;
+        let mut region = zst.reserve_region(8)?;
+        region.write(&[5; 2])?;
+        assert_eq!([5; 2], region.finish());
+        assert_eq!(2, zst.used());
+        Ok(())
+    }
+
+    #[test]
+    fn bump_shrink_last() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::new(256)?;
+        let v = bump.alloc_with_drop(vec![1u64, 2, 3])?;
+        let ptr = std::ptr::NonNull::from(&*v).cast::<u8>();
+        assert!(bump.shrink_last(ptr, 24, 0).is_err());
+        assert!(bump.shrink_last(ptr, usize::MAX, 0).is_err());
+        assert_eq!(24, bump.used());
+
+        let bytes = bump.alloc([0xAAu8; 16])?;
+        let ptr = std::ptr::NonNull::from(&*bytes).cast::<u8>();
+        bump.shrink_last(ptr, 16, 4)?;
+        assert_eq!(28, bump.used());
+        bump.clear();
+        Ok(())
+    }
+
+    #[test]
+    fn bump_rewind_runs_post_marker_drops() -> anyhow::Result<()> {
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        let mut bump = BumpAllocator::new(256)?;
+        bump.alloc_with_drop(Logged("a", log.clone()))?;
+        bump.alloc_with_drop(Logged("b", log.clone()))?;
+        let marker = bump.marker();
+        bump.alloc_with_drop(Logged("c", log.clone()))?;
+        bump.alloc_with_drop(Logged("d", log.clone()))?;
+
+        bump.rewind(marker);
+        assert_eq!(vec!["d", "c"], log.take());
+        assert_eq!(2, bump.tracked_drops());
+
+        bump.alloc_with_drop(Logged("e", log.clone()))?;
+        drop(bump);
+        assert_eq!(vec!["e", "b", "a"], log.take());
+        Ok(())
+    }
+
+    #[test]
+    fn bump_copy_out_and_move_out() -> anyhow::Result<()> {
+        let log = Rc::new(RefCell::new(Vec::new()));
+
+        let mut bump = BumpAllocator::new(256)?;
+        let name = bump.alloc_with_drop(String::from("arena"))?;
+        let owned = bump.copy_out(&name);
+        assert_eq!("arena", owned);
+        assert_eq!("arena", *name);
+
+        let marker = bump.marker();
+        let ptr = bump.alloc_with_drop(Logged("moved", log.clone()))?;
+        let moved = unsafe { bump.move_out(ptr) };
+        assert_eq!(marker, bump.used());
+        assert_eq!(1, bump.tracked_drops());
+
+        bump.clear();
+        assert!(log.borrow().is_empty());
+        drop(moved);
+        assert_eq!(vec!["moved"], log.take());
+        Ok(())
+    }
+
+    #[test]
+    fn bump_optimized_for() -> anyhow::Result<()> {
+        use std::alloc::Layout;
+
+        let layouts = [
+            Layout::new::<u8>(),
+            Layout::new::<u64>(),
+            Layout::new::<u16>(),
+        ];
+        // Parent arena knocked off 8-byte alignment, so a default child base
+        // lands on an odd address.
+        let mut parent = BumpAllocator::with_align(256, 8)?;
+        parent.alloc(0u8)?;
+        let mut default = parent.sub(64)?;
+        let mut optimized = BumpAllocator::optimized_for(64, &layouts)?;
+
+        crate::alloc!(default, _a = 1u64, _b = 2u16, _c = 3u8);
+        crate::alloc!(optimized, _a = 1u64, _b = 2u16, _c = 3u8);
+        assert_eq!(11, optimized.used());
+        assert!(default.used() > optimized.used());
+        Ok(())
+    }
+
+    #[test]
+    fn pool_partition() -> anyhow::Result<()> {
+        let mut pool = PoolAllocator::<u32>::new(8);
+        let ptrs = (0..8)
+            .map(|i| pool.alloc(i))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        for ptr in ptrs.into_iter().filter(|p| p.slot() % 2 == 1) {
+            pool.dealloc(ptr);
+        }
+
+        let (live, free) = pool.partition();
+        let live = live.copied().collect::<Vec<_>>();
+        let mut free = free.collect::<Vec<_>>();
+        assert_eq!(vec![0, 2, 4, 6], live);
+        free.sort();
+        assert_eq!(vec![1, 3, 5, 7], free);
+        Ok(())
+    }
+
+    #[cfg(all(feature = "mmap", unix))]
+    #[test]
+    fn bump_protect_readonly() -> anyhow::Result<()> {
+        let page = crate::page_size();
+        let mut bump = BumpAllocator::with_align(page * 2, page)?;
+        let table = bump.alloc_slice(&[1u32, 2, 3, 4])?.as_ptr();
+
+        bump.protect_readonly()?;
+        let table = unsafe { std::slice::from_raw_parts(table, 4) };
+        assert_eq!(10, table.iter().sum::<u32>());
+        assert!(bump.alloc(5u32).is_err());
+
+        bump.unprotect();
+        bump.alloc(5u32)?;
+        bump.protect_readonly()?;
+        bump.with_remaining(|rest| rest.fill(7));
+        bump.alloc(6u32)?;
+
+        bump.protect_readonly()?;
+        bump.clear_policy = ClearPolicy::Poison;
+        bump.clear();
+        assert_eq!(0, bump.used());
+
+        // A partly used page would lock memory the arena doesn't own.
+        let mut small = BumpAllocator::new(100)?;
+        small.alloc(1u8)?;
+        assert!(small.protect_readonly().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn bump_alloc_uninit_slice() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::with_align(64, 8)?;
+        bump.alloc(1u8)?;
+        assert!(bump.alloc_uninit_slice::<u64>(0)?.is_empty());
+        assert_eq!(1, bump.used());
+
+        let slots = bump.alloc_uninit_slice::<u64>(4)?;
+        assert!(slots.as_ptr().is_aligned());
+        for (i, slot) in slots.iter_mut().enumerate() {
+            slot.write(i as u64 * 10);
+        }
+        let values = unsafe { &*(slots as *const [_] as *const [u64]) };
+        assert_eq!([0, 10, 20, 30], values);
+        assert_eq!(40, bump.used());
+
+        assert!(bump.alloc_uninit_slice::<u64>(4).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn bump_ids_are_distinct_and_stable() -> anyhow::Result<()> {
+        let mut arenas = [
+            BumpAllocator::new(64)?,
+            BumpAllocator::new(64)?,
+            BumpAllocator::new(64)?,
+        ];
+        let ids = arenas.each_ref().map(BumpAllocator::id);
+        assert!(ids[0] != ids[1] && ids[1] != ids[2] && ids[0] != ids[2]);
+
+        for arena in &mut arenas {
+            arena.alloc(1u32)?;
+            arena.rewind(0);
+            arena.clear();
+        }
+        assert_eq!(ids, arenas.each_ref().map(BumpAllocator::id));
+        assert!(!ids.contains(&arenas[0].clone().id()));
+        Ok(())
+    }
+
+    #[test]
+    fn bump_alloc_with_finalizer() -> anyhow::Result<()> {
+        let closed = Rc::new(Cell::new(None));
+
+        let mut bump = BumpAllocator::new(64)?;
+        let handle = {
+            let closed = closed.clone();
+            bump.alloc_with_finalizer(7u32, move |fd| closed.set(Some(*fd)))?
+        };
+        assert_eq!(7, *handle);
+        assert_eq!(None, closed.get());
+
+        bump.clear();
+        assert_eq!(Some(7), closed.get());
+        assert_eq!(0, bump.tracked_drops());
+        Ok(())
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn pool_rebuild_free_list_after_raw_restore() -> anyhow::Result<()> {
+        let mut src = PoolAllocator::<u64>::new(6);
+        let ptrs = (0..4)
+            .map(|i| src.alloc(i))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        src.dealloc(ptrs[1].clone());
+        let snapshot = src.snapshot();
+
+        let mut dst = PoolAllocator::<u64>::new(6);
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                snapshot.bytes.as_ptr(),
+                dst.buf.cast::<u8>(),
+                snapshot.bytes.len(),
+            )
+        };
+        assert!(dst.check_invariants().is_err());
+
+        dst.rebuild_free_list();
+        dst.check_invariants()?;
+        assert_eq!(vec![0, 2, 3], dst.iter().copied().collect::<Vec<_>>());
+        for v in 10..13 {
+            dst.alloc(v)?;
+        }
+        assert!(dst.alloc(13).is_err());
+        dst.check_invariants()?;
+        Ok(())
+    }
+
+    #[test]
+    fn bump_clear_cost() -> anyhow::Result<()> {
+        let mut pod = BumpAllocator::new(64)?;
+        pod.alloc(1u32)?;
+        pod.alloc([0u8; 8])?;
+        assert_eq!(ClearCost::Trivial, pod.clear_cost());
+
+        let mut owning = BumpAllocator::new(256)?;
+        for i in 0..3 {
+            owning.alloc_with_drop(vec![i])?;
+        }
+        assert_eq!(ClearCost::Drops(3), owning.clear_cost());
+        owning.clear();
+        assert_eq!(ClearCost::Trivial, owning.clear_cost());
+        Ok(())
+    }
+
+    #[test]
+    fn rel_ptr_survives_buffer_copy() -> anyhow::Result<()> {
+        struct Node {
+            value: u32,
+            next: RelPtr<Node>,
+        }
+
+        let mut bump = BumpAllocator::with_align(128, 8)?;
+        let mut head = bump.alloc(Node {
+            value: 1,
+            next: RelPtr::null(),
+        })?;
+        let tail = bump.alloc(Node {
+            value: 2,
+            next: RelPtr::null(),
+        })?;
+        head.next.set(&tail);
+        let head_offset = bump.offset_of(&head).expect("head is in the arena");
+
+        let copy = bump.try_clone()?;
+        drop(bump);
+        let head = unsafe { copy.ptr_from_offset::<Node>(head_offset) }.expect("head was copied");
+        let tail = unsafe { head.next.get(&copy) };
+        assert_eq!([1, 2], [head.value, tail.value]);
+        assert!(tail.next.is_null());
+        let range = copy.as_uninit_bytes().as_ptr_range();
+        assert!(range.contains(&(tail as *const Node).cast()));
+
+        #[repr(C)]
+        struct Looped {
+            me: RelPtr<Looped>,
+            tag: u32,
+        }
+        let mut solo = BumpAllocator::with_align(16, 8)?;
+        let mut looped = solo.alloc(Looped {
+            me: RelPtr::null(),
+            tag: 5,
+        })?;
+        looped.me.offset = 0;
+        assert!(!looped.me.is_null());
+        assert_eq!(5, unsafe { looped.me.get(&solo) }.tag);
+        Ok(())
+    }
+
+    #[test]
+    fn pool_absorb() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::new(1024)?;
+        let buf = bump.alloc_layout(PoolAllocator::<u64>::buffer_layout(8))?;
+        let upper = unsafe {
+            buf.cast::<u8>()
+                .add(PoolAllocator::<u64>::buffer_layout(4).size())
+        };
+        let mut low = unsafe { PoolAllocator::<u64>::from_raw_parts(buf, 4) };
+        let mut high = unsafe { PoolAllocator::<u64>::from_raw_parts(upper, 4) };
+
+        let a = low.alloc(1)?;
+        let b = high.alloc(2)?;
+        let c = high.alloc(3)?;
+        high.dealloc(b);
+        bump.alloc(0u64)?;
+        let elsewhere = bump.alloc_layout(PoolAllocator::<u64>::buffer_layout(4))?;
+        let detached = unsafe { PoolAllocator::<u64>::from_raw_parts(elsewhere, 4) };
+        assert!(high.absorb(detached).is_err());
+        assert!(PoolAllocator::<u64>::new(2)
+            .absorb(PoolAllocator::new(2))
+            .is_err());
+
+        low.absorb(high)?;
+        low.check_invariants()?;
+        assert_eq!([1, 3], [*a, *c]);
+        assert_eq!(5, c.slot());
+        for v in 10..16 {
+            low.alloc(v)?;
+        }
+        assert!(low.alloc(16).is_err());
+        assert_eq!(8, low.iter().count());
+        Ok(())
+    }
+
+    #[test]
+    fn typed_bump_push_get() -> anyhow::Result<()> {
+        let mut names = TypedBump::new(4)?;
+        let handles = ["a", "b", "c"]
+            .map(|n| names.push(n.to_string()))
+            .into_iter()
+            .collect::<anyhow::Result<Vec<u32>>>()?;
+        assert_eq!(vec![0, 1, 2], handles);
+
+        let resolved = handles.iter().filter_map(|&h| names.get(h));
+        assert_eq!(vec!["a", "b", "c"], resolved.collect::<Vec<_>>());
+        assert_eq!(None, names.get(3));
+
+        names.clear();
+        assert_eq!(None, names.get(handles[0]));
+        Ok(())
+    }
+
+    #[test]
+    fn request_arena_epochs_do_not_bleed() -> anyhow::Result<()> {
+        let mut arena = RequestArena::new(64)?;
+        let stale = {
+            let mut req = arena.start();
+            let body = req.alloc(0xAAAA_AAAAu32)?;
+            req.stamp(&body).expect("body is in the arena")
+        };
+
+        let mut req = arena.start();
+        assert_eq!(0, req.used());
+        assert!(unsafe { req.resolve::<u32>(stale) }.is_err());
+        let body = req.alloc(7u32)?;
+        assert_eq!(7, *body);
+        drop(req);
+        assert_eq!(2, arena.requests());
+        Ok(())
+    }
+
+    #[test]
+    fn bump_reclaim_size() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::with_align(64, 8)?;
+        bump.alloc(1u8)?;
+        let marker = bump.marker();
+        assert_eq!(0, bump.reclaim_size(marker));
+
+        bump.alloc(2u64)?;
+        bump.alloc([3u8; 5])?;
+        assert_eq!(20, bump.reclaim_size(marker));
+        assert_eq!(bump.used(), bump.reclaim_size(0));
+
+        bump.rewind(marker);
+        assert_eq!(1, bump.used());
+        Ok(())
+    }
+
+    #[test]
+    fn shared_arena_outlived_by_handle() -> anyhow::Result<()> {
+        let arena = SharedArena::new(64)?;
+        let a = arena.alloc(41u32)?;
+        let b = arena.alloc([1u8, 2, 3])?;
+        assert!(arena.clear().is_err());
+
+        drop(b);
+        drop(arena);
+        assert_eq!(41, *a);
+        assert_eq!(42, *a + 1);
+        Ok(())
+    }
+
+    #[test]
+    fn bump_alloc_dyn_boxed() -> anyhow::Result<()> {
+        trait Shape {
+            fn area(&self) -> u32;
+        }
+        struct Square(u32, Rc<Cell<bool>>);
+        impl Shape for Square {
+            fn area(&self) -> u32 {
+                self.0 * self.0
+            }
+        }
+        impl Drop for Square {
+            fn drop(&mut self) {
+                self.1.set(true);
+            }
+        }
+        let dropped = Rc::new(Cell::new(false));
+
+        let mut bump = BumpAllocator::new(128)?;
+        let shape = bump.alloc_dyn_boxed(Square(3, dropped.clone()), |s| s as &mut dyn Shape)?;
+        assert_eq!(9, shape.area());
+        assert_eq!(1, bump.tracked_drops());
+
+        bump.clear();
+        assert!(dropped.get());
+        Ok(())
+    }
+
+    #[test]
+    fn stack_usable_capacity() {
+        const USABLE: usize = StackAllocator::<100>::usable_capacity::<u64>();
+        const _: () = assert!(USABLE == 93);
+        assert_eq!(0, StackAllocator::<4>::usable_capacity::<u64>());
+        assert_eq!(4, StackAllocator::<4>::usable_capacity::<u8>());
+
+        let mut sa = StackAllocator::<100>::new();
+        let mut fitted = 0;
+        while sa.alloc(0u64).is_ok() {
+            fitted += 1;
+        }
+        assert!(fitted >= USABLE / 8);
+    }
+
+    #[test]
+    fn bump_alloc_ffi() -> anyhow::Result<()> {
+        static FREED: AtomicUsize = AtomicUsize::new(0);
+        unsafe extern "C" fn mock_free(handle: *mut usize) {
+            FREED.fetch_add(*handle, Ordering::SeqCst);
+        }
+
+        let mut bump = BumpAllocator::new(64)?;
+        bump.alloc_ffi(1usize, mock_free)?;
+        bump.alloc_ffi(10usize, mock_free)?;
+        assert_eq!(0, FREED.load(Ordering::SeqCst));
+
+        bump.clear();
+        assert_eq!(11, FREED.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    #[test]
+    fn scoped_builder_depth_limit() -> anyhow::Result<()> {
+        struct Node {
+            depth: usize,
+            child: Option<crate::BumpPtr<Node>>,
+        }
+        fn build(b: &mut ScopedBuilder, levels: usize) -> anyhow::Result<crate::BumpPtr<Node>> {
+            b.nest(|b| {
+                let depth = b.depth();
+                let child = match levels {
+                    0 => None,
+                    n => Some(build(b, n - 1)?),
+                };
+                b.alloc(Node { depth, child })
+            })
+        }
+
+        let mut bump = BumpAllocator::new(4096)?;
+        bump.alloc(0u8)?;
+        let before = bump.used();
+
+        let mut builder = ScopedBuilder::new(&mut bump, 256);
+        let root = build(&mut builder, 3)?;
+        assert_eq!(2, root.child.as_ref().map(|c| c.depth).unwrap_or_default());
+        assert_eq!(0, builder.depth());
+        let shallow = builder.used_bytes();
+
+        assert!(build(&mut builder, 1000).is_err());
+        assert_eq!(0, builder.depth());
+        assert_eq!(shallow, builder.used_bytes());
+        assert_eq!(before + shallow, bump.used());
+        Ok(())
+    }
+
+    #[test]
+    fn bump_alloc_matrix() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::new(128)?;
+        bump.alloc(1u8)?;
+        let mut m = bump.alloc_matrix(3, 3, 0.0f32)?;
+        assert!(m.as_slice().as_ptr().is_aligned());
+        for i in 0..3 {
+            m[(i, i)] = 1.0 + i as f32;
+        }
+        *m.get_mut(0, 2).expect("in bounds") = 9.0;
+
+        assert_eq!([1.0, 0.0, 9.0], m.row(0));
+        assert_eq!([2.0, 3.0], [m[(1, 1)], m[(2, 2)]]);
+        assert_eq!(None, m.get(3, 0));
+        assert_eq!(None, m.get(0, 3));
+        assert_eq!((3, 3), (m.rows(), m.cols()));
+        Ok(())
+    }
+
+    #[test]
+    fn multi_marker_rewinds_all() -> anyhow::Result<()> {
+        use crate::MultiMarker;
+
+        let mut ast = BumpAllocator::new(128)?;
+        let mut scratch = BumpAllocator::new(128)?;
+        ast.alloc(1u32)?;
+        scratch.alloc([0u8; 3])?;
+        let marker = MultiMarker::capture(&[&ast, &scratch]);
+
+        ast.alloc(2u64)?;
+        scratch.alloc([0u8; 40])?;
+        let mut other = BumpAllocator::new(16)?;
+        assert!(marker.rewind_all(&mut [&mut ast, &mut other]).is_err());
+        assert!(ast.used() > 4);
+
+        marker.rewind_all(&mut [&mut scratch, &mut ast])?;
+        assert_eq!([4, 3], [ast.used(), scratch.used()]);
+        Ok(())
+    }
+
+    #[test]
+    fn pool_slot_id_round_trip() -> anyhow::Result<()> {
+        let mut pool = PoolAllocator::<u64>::new(4);
+        pool.alloc(1)?;
+        let entity = pool.alloc(2)?;
+        let id = pool.slot_id(&entity);
+        assert_eq!(1, id);
+        assert_eq!(Some(entity.clone()), pool.ptr_from_slot_id(id));
+        assert!(pool.ptr_from_slot_id(4).is_none());
+
+        pool.dealloc(entity.clone());
+        assert!(pool.ptr_from_slot_id(id).is_none());
+
+        let reused = pool.alloc(3)?;
+        assert_eq!(id, pool.slot_id(&reused));
+        let resolved = pool.ptr_from_slot_id(id).expect("slot is live again");
+        assert_eq!(reused, resolved);
+        assert_ne!(entity, resolved);
+        Ok(())
+    }
+
+    #[cfg(feature = "debug-trace")]
+    #[test]
+    fn bump_sweep() -> anyhow::Result<()> {
+        use std::alloc::Layout;
+
+        let mut bump = BumpAllocator::with_align(128, 8)?;
+        let a = bump.alloc(1u8)?;
+        bump.alloc(2u64)?;
+        let marker = bump.marker();
+        bump.alloc([3u16; 3])?;
+
+        let swept = bump.sweep().collect::<Vec<_>>();
+        assert_eq!(3, swept.len());
+        assert_eq!(a.ptr.cast::<u8>(), swept[0].0.as_ptr());
+        assert_eq!(
+            vec![
+                Layout::new::<u8>(),
+                Layout::new::<u64>(),
+                Layout::new::<[u16; 3]>()
+            ],
+            swept.iter().map(|&(_, layout)| layout).collect::<Vec<_>>()
+        );
+
+        bump.rewind(marker);
+        assert_eq!(2, bump.sweep().count());
+        Ok(())
+    }
+
+    #[test]
+    fn bump_arena_slice() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::new(64)?;
+        let mut slice = bump.alloc_slice(&[1u16, 2, 3])?;
+        assert_eq!(3, slice.len());
+        assert!(!slice.is_empty());
+        slice[1] = 20;
+        assert_eq!([1, 20, 3], *slice);
+        assert_eq!(23, slice.iter().skip(1).sum::<u16>());
+
+        let plain: &mut [u16] = slice.into_slice();
+        assert_eq!(3, plain.len());
+        assert!(bump.alloc_slice::<u8>(&[])?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn static_stack() -> anyhow::Result<()> {
+        use crate::StaticStack;
+
+        static ALLOC: StaticStack<1024> = StaticStack::new();
+        let value = ALLOC.with(|stack| stack.alloc(0xBEEFu32).map(|p| *p))?;
+        assert_eq!(0xBEEF, value);
+
+        let nested = std::panic::catch_unwind(|| ALLOC.with(|_| ALLOC.with(|_| ())));
+        assert!(nested.is_err());
+        assert!(ALLOC.with(|stack| stack.alloc(1u8).is_ok()));
+
+        let elsewhere = std::thread::spawn(|| ALLOC.with(|stack| stack.clear())).join();
+        assert!(elsewhere.is_err());
+        assert_ne!(0, ALLOC.with(|stack| stack.used()));
+        Ok(())
+    }
+
+    #[test]
+    fn bump_max_padding() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::with_align(128, 8)?;
+        bump.set_max_padding(8);
+        for _ in 0..2 {
+            bump.alloc(1u8)?;
+            bump.alloc(2u32)?;
+        }
+        assert_eq!(6, bump.padding());
+        bump.alloc(3u8)?;
+        assert_eq!(
+            Err(crate::AllocError::ExceedsMaxPadding),
+            bump.try_alloc(4u64).map(|_| ())
+        );
+        assert_eq!(6, bump.padding());
+
+        bump.clear();
+        assert_eq!(0, bump.padding());
+        for _ in 0..4 {
+            bump.alloc(2u32)?;
+        }
+        for _ in 0..4 {
+            bump.alloc(1u8)?;
+        }
+        bump.alloc(3u64)?;
+        assert_eq!(4, bump.padding());
+        Ok(())
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test]
+    fn bump_guarded_leak_detector() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::new(64)?;
+        let before = crate::leaked_guards();
+
+        let kept = bump.alloc_guarded(5u32)?;
+        assert_eq!(5, *kept);
+        assert_eq!(5, *kept.finish());
+        assert_eq!(before, crate::leaked_guards());
+
+        let forgotten = bump.alloc_guarded(6u32)?;
+        drop(forgotten);
+        assert_eq!(before + 1, crate::leaked_guards());
+        Ok(())
+    }
+
+    #[test]
+    fn promote_bump_value_to_pool() -> anyhow::Result<()> {
+        let mut frame = BumpAllocator::new(64)?;
+        let mut pool = PoolAllocator::<[u32; 2]>::new(1);
+
+        let entity = frame.alloc([7, 8])?;
+        let promoted = unsafe { crate::promote_to_pool(&mut frame, entity, &mut pool) }
+            .expect("pool has room");
+        frame.clear();
+        assert_eq!([7, 8], *promoted);
+        assert_eq!(vec![[7, 8]], pool.iter().copied().collect::<Vec<_>>());
+
+        let other = frame.alloc([9, 9])?;
+        let used = frame.used();
+        assert!(unsafe { crate::promote_to_pool(&mut frame, other, &mut pool) }.is_none());
+        assert_eq!(used, frame.used());
+
+        let shared = Rc::new(());
+        let mut rcs = PoolAllocator::<Rc<()>>::new(1);
+        let tracked = frame.alloc_with_drop(Rc::clone(&shared))?;
+        unsafe { crate::promote_to_pool(&mut frame, tracked, &mut rcs) }.expect("pool has room");
+        frame.clear();
+        assert_eq!(2, Rc::strong_count(&shared));
+        drop(rcs);
+        assert_eq!(1, Rc::strong_count(&shared));
+        Ok(())
+    }
+
+    #[test]
+    fn bump_hexdump() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::new(64)?;
+        bump.alloc_slice(b"Hello, arena!\0\x01\x7f")?;
+        bump.alloc_slice(&[0xABu8, 0xCD])?;
+
+        let dump = unsafe { bump.hexdump() };
+        let lines = dump.lines().collect::<Vec<_>>();
+        assert_eq!(2, lines.len());
+        assert_eq!(
+            "00000000: 4865 6c6c 6f2c 2061 7265 6e61 2100 017f  Hello, arena!...",
+            lines[0]
+        );
+        assert!(lines[1].starts_with("00000010: abcd "));
+        assert!(lines[1].ends_with("  .."));
+        Ok(())
+    }
+
+    #[test]
+    fn slab_registry_pools_per_type() -> anyhow::Result<()> {
+        let mut slabs = crate::SlabRegistry::new(4);
+        let a = slabs.alloc(1u32)?;
+        let b = slabs.alloc(2u32)?;
+        let name = slabs.alloc(String::from("slab"))?;
+        assert_eq!(2, slabs.pool_count());
+        assert_eq!((1, 2), (*a, *b));
+        assert_eq!("slab", *name);
+
+        let ints = slabs.pool::<u32>().expect("u32 pool exists");
+        assert_eq!(vec![1, 2], ints.iter().copied().collect::<Vec<_>>());
+        assert_eq!(
+            1,
+            slabs
+                .pool::<String>()
+                .map(|p| p.iter().count())
+                .unwrap_or(0)
+        );
+        assert!(slabs.pool::<u64>().is_none());
+
+        slabs.pool_mut::<u32>().expect("u32 pool exists").dealloc(a);
+        assert_eq!(
+            1,
+            slabs.pool::<u32>().map(|p| p.iter().count()).unwrap_or(0)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn slab_registry_reserve() -> anyhow::Result<()> {
+        let mut slabs = crate::SlabRegistry::new(2);
+        slabs.reserve::<u64>(8)?;
+        slabs.reserve::<u64>(3)?;
+
+        let before = heap_allocs();
+        for i in 0..8 {
+            slabs.alloc(i as u64)?;
+        }
+        assert_eq!(before, heap_allocs());
+        assert!(slabs.alloc(8u64).is_err());
+        assert!(slabs.reserve::<u64>(1).is_err());
+
+        slabs.alloc(1u8)?;
+        assert!(slabs.reserve::<u8>(4).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn display_summaries() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::new(200)?;
+        bump.alloc([0u8; 50])?;
+        bump.alloc(1u8)?;
+        assert_eq!(
+            "BumpAllocator(used=51/200 bytes, 25% full, 2 allocs)",
+            bump.to_string()
+        );
+
+        let mut stack = StackAllocator::<64>::new();
+        stack.alloc([0u8; 16])?;
+        assert_eq!(
+            "StackAllocator(used=16/64 bytes, 25% full, 1 allocs)",
+            stack.to_string()
+        );
+
+        let mut pool = PoolAllocator::<u32>::new(8);
+        pool.alloc(1)?;
+        pool.alloc(2)?;
+        assert_eq!("PoolAllocator(live=2/8 slots, 25% full)", pool.to_string());
+
+        let mut double = DoubleBumpAllocator::new(32)?;
+        double.current_mut().alloc(0u32)?;
+        double.swap();
+        assert_eq!(
+            "DoubleBumpAllocator(active=1, current=0/32 bytes, previous=4/32 bytes)",
+            double.to_string()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn frame_manager_end_frame() -> anyhow::Result<()> {
+        let mut frames = crate::FrameManager::new();
+        let scratch = frames.register_scratch(BumpAllocator::new(64)?);
+        let double = frames.register_double(DoubleBumpAllocator::new(64)?);
+        let persistent = frames.register_persistent(BumpAllocator::new(64)?);
+
+        let arena = |f: &mut crate::FrameManager| f.scratch(scratch).map(|a| a.used());
+        frames.scratch(scratch).expect("registered").alloc(1u32)?;
+        frames
+            .double(double)
+            .expect("registered")
+            .current_mut()
+            .alloc(2u64)?;
+        frames
+            .persistent(persistent)
+            .expect("registered")
+            .alloc(3u16)?;
+
+        frames.end_frame();
+        assert_eq!(Some(0), arena(&mut frames));
+        let db = frames.double(double).expect("registered");
+        assert_eq!((0, 8), (db.current().used(), db.previous().used()));
+        assert_eq!(2, frames.persistent(persistent).expect("registered").used());
+
+        frames.end_frame();
+        let db = frames.double(double).expect("registered");
+        assert_eq!((0, 0), (db.current().used(), db.previous().used()));
+        assert_eq!(2, frames.persistent(persistent).expect("registered").used());
+        assert_eq!(2, frames.frame());
+        Ok(())
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn pool_live_checksum() -> anyhow::Result<()> {
+        let mut a = PoolAllocator::<u64>::new(6);
+        for v in [1, 2, 3] {
+            a.alloc(v)?;
+        }
+
+        let mut b = PoolAllocator::<u64>::new(6);
+        let temp = b.alloc(9)?;
+        b.alloc(2)?;
+        let scratch = b.alloc(7)?;
+        b.dealloc(scratch);
+        b.alloc(3)?;
+        b.dealloc(temp);
+        b.alloc(1)?;
+
+        assert_ne!(a.snapshot().bytes, b.snapshot().bytes);
+        assert_eq!(a.live_checksum(), b.live_checksum());
+
+        b.alloc(4)?;
+        assert_ne!(a.live_checksum(), b.live_checksum());
+        Ok(())
+    }
+
+    #[test]
+    fn bump_alloc_aligned_like() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::with_align(512, 64)?;
+        bump.alloc(0u8)?;
+        let lock = bump.alloc_layout(std::alloc::Layout::from_size_align(1, 64)?)?;
+        let lock = crate::RadPtr { ptr: lock.as_ptr() };
+        assert!((lock.ptr as usize).is_multiple_of(64));
+
+        bump.alloc(0u8)?;
+        let counter = bump.alloc_aligned_like(5u32, &lock)?;
+        assert!((counter.ptr as usize).is_multiple_of(64));
+        assert_eq!(5, *counter);
+
+        let half = crate::RadPtr {
+            ptr: (lock.ptr as usize + 16) as *mut u8,
+        };
+        let near = bump.alloc_aligned_like(1u8, &half)?;
+        assert!((near.ptr as usize).is_multiple_of(16));
+        Ok(())
+    }
+
+    #[test]
+    fn bump_to_boxed_slice() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::new(64)?;
+        bump.alloc_slice(&[1u8, 2, 3])?;
+        bump.alloc_slice(b"arena")?;
+        let owned = unsafe { bump.to_boxed_slice() };
+        assert_eq!(unsafe { bump.as_bytes() }, &*owned);
+        assert_ne!(bump.as_uninit_bytes().as_ptr(), owned.as_ptr().cast());
+
+        bump.clear();
+        bump.alloc_slice(&[9u8; 8])?;
+        drop(bump);
+        assert_eq!(b"\x01\x02\x03arena", &*owned);
+        Ok(())
+    }
+
+    #[test]
+    fn free_list_truncate_slice() -> anyhow::Result<()> {
+        let mut fl = FreeListAllocator::new(64)?;
+        let samples = fl.alloc_slice(&[1u32, 2, 3, 4, 5, 6, 7, 8])?;
+        let _after = fl.alloc([0u8; 32])?;
+        assert_eq!(0, fl.free_bytes());
+
+        let samples = fl.truncate_slice(samples, 2)?;
+        assert_eq!([1, 2], *samples);
+        assert_eq!(24, fl.free_bytes());
+
+        let reused = fl.alloc_slice(&[9u32; 6])?;
+        assert_eq!(unsafe { samples.as_ptr().add(2) }, reused.as_ptr());
+        assert_eq!(0, fl.free_bytes());
+
+        fl.dealloc_slice(samples)?;
+        assert_eq!(8, fl.free_bytes());
+        let pair = fl.alloc_slice(&[1u32, 2])?;
+        assert!(fl.truncate_slice(pair, 3).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn bump_cell_is_borrowed() -> anyhow::Result<()> {
+        use std::alloc::{GlobalAlloc, Layout};
+
+        let cell = BumpCell::new(BumpAllocator::new(64)?);
+        assert!(!cell.is_borrowed());
+        assert!(cell.with(|_| cell.is_borrowed()));
+
+        let layout = Layout::new::<u64>();
+        let ptr = unsafe { GlobalAlloc::alloc(&cell, layout) };
+        assert!(!ptr.is_null());
+        assert!(!cell.is_borrowed());
+        unsafe { GlobalAlloc::dealloc(&cell, ptr, layout) };
+        assert!(!cell.is_borrowed());
+        assert!(cell.with(|_| unsafe { GlobalAlloc::alloc(&cell, layout) }.is_null()));
+        Ok(())
+    }
+
+    #[cfg(all(feature = "mmap", unix))]
+    #[test]
+    #[ignore = "forks a child that faults: run with --ignored"]
+    fn bump_new_guarded_faults_past_capacity() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::new_guarded(100)?;
+        assert_eq!(crate::page_size(), bump.capacity());
+        bump.alloc_slice(&vec![7u8; bump.capacity()])?;
+        assert!(bump.alloc(0u8).is_err());
+
+        let past_end = unsafe { bump.as_bytes().as_ptr().add(bump.capacity()) }.cast_mut();
+        unsafe {
+            let pid = libc::fork();
+            assert!(pid >= 0);
+            if pid == 0 {
+                past_end.write_volatile(1);
+                libc::_exit(0);
+            }
+            let mut status = 0;
+            assert_eq!(pid, libc::waitpid(pid, &mut status, 0));
+            assert!(libc::WIFSIGNALED(status));
+            assert_eq!(libc::SIGSEGV, libc::WTERMSIG(status));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn bump_offset_handle_snapshot_restore() -> anyhow::Result<()> {
+        let mut bump = BumpAllocator::new(64)?;
+        bump.clear();
+        let _pad = bump.alloc(1u8)?;
+        let handle = bump.alloc_offset(0xABCDu32)?;
+        let snapshot = unsafe { bump.snapshot() };
+        assert_eq!(1, snapshot.epoch);
+        assert_eq!(snapshot.epoch, handle.raw().epoch);
+
+        let mut restored = BumpAllocator::new(64)?;
+        assert!(unsafe { handle.resolve(&restored) }.is_none());
+        restored.restore(&snapshot)?;
+        assert_eq!(Some(&0xABCD), unsafe { handle.resolve(&restored) });
+        assert_eq!(bump.used(), restored.used());
+
+        restored.clear();
+        assert!(unsafe { handle.resolve(&restored) }.is_none());
+        assert!(BumpAllocator::new(4)?.restore(&snapshot).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn stack_commit_external_write() -> anyhow::Result<()> {
+        let mut stack = StackAllocator::<32>::new();
+        stack.alloc(1u8)?;
+
+        let spare = stack.remaining_mut();
+        assert_eq!(31, spare.len());
+        for (dst, &src) in spare.iter_mut().zip(b"dma!") {
+            dst.write(src);
+        }
+        unsafe { stack.commit(4)? };
+        assert_eq!(5, stack.used());
+        assert_eq!(b"dma!", unsafe {
+            crate::assume_init_bytes(&stack.stack[1..5])
+        });
+
+        assert!(unsafe { stack.commit(28) }.is_err());
+        stack.popn(4)?;
+        assert_eq!(1, stack.used());
+        Ok(())
+    }
+
+    #[test]
+    fn stack_alloc_with_drop_survives_move() -> anyhow::Result<()> {
+        #[repr(align(16))]
+        struct Wide(Rc<Cell<u32>>);
+        impl Drop for Wide {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+        let drops = Rc::new(Cell::new(0));
+
+        let mut stack = StackAllocator::<128>::new();
+        stack.alloc(1u8)?;
+        stack.alloc_with_drop(Rc::clone(&drops))?;
+        assert!(stack.alloc_with_drop(Wide(drops.clone())).is_err());
+        assert_eq!(1, drops.get());
+
+        let moved = Box::new(stack);
+        assert_eq!(2, Rc::strong_count(&drops));
+        drop(moved);
+        assert_eq!(1, Rc::strong_count(&drops));
+        Ok(())
+    }
 }